@@ -1,11 +1,77 @@
+use libmpv::{
+    protocol::{RangeProvider, ZipProvider},
+    LoadFile, MpvBuilder,
+};
+
 mod gui;
 mod renderer;
 mod window;
 
-fn main() {
+/// Split a `--name=value` argument into an mpv option, where `--name` means `yes` and
+/// `--no-name` means `no`, like on mpv's command line. Returns `None` if there is no name.
+fn parse_option(arg: &str) -> Option<(&str, &str)> {
+    let option = arg.strip_prefix("--")?;
+    let (name, value) = match option.find('=') {
+        Some(idx) => (&option[..idx], &option[idx + 1..]),
+        None => match option.strip_prefix("no-") {
+            Some(name) => (name, "no"),
+            None => (option, "yes"),
+        },
+    };
+    if name.is_empty() {
+        None
+    } else {
+        Some((name, value))
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    // mpv's log messages are forwarded to `log`, e.g. `RUST_LOG=ffmpeg=debug`.
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Everything after a `--` is a path, even if it starts with `--`.
+    let (args, rest) = match args.iter().position(|arg| arg == "--") {
+        Some(idx) => (&args[..idx], &args[idx + 1..]),
+        None => (&args[..], &[][..]),
+    };
+    let (options, mut paths): (Vec<_>, Vec<_>) = args.iter().partition(|arg| arg.starts_with("--"));
+    paths.extend(rest);
+
+    let mut builder = MpvBuilder::embedded();
+    for arg in options {
+        let (name, value) = match parse_option(arg) {
+            Some(option) => option,
+            None => {
+                log::warn!("ignoring {}, which has no option name", arg);
+                continue;
+            }
+        };
+        // Video is rendered into kawa's window, which these options would replace.
+        if name == "vo" || name == "wid" {
+            log::warn!("ignoring --{}, video is rendered into the window", name);
+            continue;
+        }
+        builder = builder.option(name, value);
+    }
+    let mpv = builder.build()?;
+
+    // Allows playing media packaged in asset bundles, e.g. `zip://assets.zip#intro.mkv`.
+    let protocols = mpv.create_protocol_context();
+    protocols.register("zip", ZipProvider)?;
+    protocols.register("range", RangeProvider)?;
+
     unsafe {
-        let window = window::Window::create("kawa").unwrap();
+        let window = window::Window::create("kawa", &mpv)?;
 
+        if let Some(path) = paths.first() {
+            mpv.load_file(&LoadFile::new(path).append_play())?;
+        }
+
+        // Returns once the window is closed. The render context is freed first, while its GL
+        // context is still alive and current, and both are dropped before `mpv`.
         window.run_event_loop();
     }
+
+    Ok(())
 }
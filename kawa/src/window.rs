@@ -4,28 +4,45 @@ use glutin::{
     dpi::LogicalSize,
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    platform::run_return::EventLoopExtRunReturn,
     window::WindowBuilder,
     ContextBuilder, PossiblyCurrent, WindowedContext,
 };
 use imgui::{FontConfig, FontSource};
 use imgui_winit_support::WinitPlatform;
+use libmpv::{
+    events::{Event as MpvEvent, EventContext},
+    logging, mpv_end_file_reason, mpv_log_level,
+    render::{mpv_render_update_flag, OpenGlInitParams, RenderApiType, RenderContext, RenderParam},
+    Mpv,
+};
 
 use crate::renderer::Renderer;
 
-pub struct Window {
+#[derive(Debug, Clone)]
+pub enum UserEvent {
+    /// mpv has a new video frame, or otherwise needs to redraw.
+    RenderUpdate,
+    /// mpv has new events, which are handled on the UI thread.
+    MpvWakeup,
+}
+
+pub struct Window<'a> {
     renderer: Renderer,
     gl_context: glow::Context,
     window_context: WindowedContext<PossiblyCurrent>,
-    event_loop: EventLoop<()>,
+    render_context: RenderContext<'a>,
+    events: EventContext<'a>,
+    event_loop: EventLoop<UserEvent>,
     platform: WinitPlatform,
     imgui: imgui::Context,
 }
 
-impl Window {
-    pub unsafe fn create(title: &str) -> Result<Self> {
+impl<'a> Window<'a> {
+    pub unsafe fn create(title: &str, mpv: &'a Mpv) -> Result<Self> {
         let window_size = LogicalSize::new(1280.0, 720.0);
 
-        let event_loop = EventLoop::new();
+        let event_loop = EventLoop::with_user_event();
 
         let window_builder = WindowBuilder::new()
             .with_title(title)
@@ -68,10 +85,35 @@ impl Window {
 
         let renderer = Renderer::init(&gl_context, &mut imgui).unwrap();
 
+        let get_proc_address =
+            |name: &str| window_context.get_proc_address(name) as *mut std::ffi::c_void;
+        let mut render_context = mpv.create_render_context(vec![
+            RenderParam::ApiType(RenderApiType::OpenGl),
+            RenderParam::OpenGlInitParams(OpenGlInitParams {
+                get_proc_address: &get_proc_address,
+            }),
+        ])?;
+
+        let proxy = event_loop.create_proxy();
+        render_context.set_update_callback(move || {
+            let _ = proxy.send_event(UserEvent::RenderUpdate);
+        });
+
+        let proxy = event_loop.create_proxy();
+        let mut events = mpv.create_event_context();
+        events.set_wakeup_callback(move || {
+            let _ = proxy.send_event(UserEvent::MpvWakeup);
+        });
+        mpv.request_log_messages(mpv_log_level::Warn)?;
+        // Handle events that were queued before the wakeup callback was set.
+        let _ = event_loop.create_proxy().send_event(UserEvent::MpvWakeup);
+
         Ok(Window {
             renderer,
             gl_context,
             window_context,
+            render_context,
+            events,
             event_loop,
             imgui,
             platform,
@@ -81,14 +123,17 @@ impl Window {
     pub fn run_event_loop(self) {
         let gl_context = self.gl_context;
         let window_context = self.window_context;
-        let event_loop = self.event_loop;
+        let render_context = self.render_context;
+        let mut events = self.events;
+        let mut event_loop = self.event_loop;
         let renderer = self.renderer;
         let mut platform = self.platform;
         let mut imgui = self.imgui;
 
         let mut last_frame = std::time::Instant::now();
 
-        event_loop.run(move |event, _, control_flow| {
+        // The closure only borrows the contexts, so that they are dropped in order afterwards.
+        event_loop.run_return(|event, _, control_flow| {
             *control_flow = ControlFlow::Wait;
 
             match event {
@@ -109,12 +154,53 @@ impl Window {
 
                     window.request_redraw();
                 }
+                Event::UserEvent(UserEvent::RenderUpdate) => {
+                    let flags = render_context.update();
+                    if flags & mpv_render_update_flag::Frame as u64 != 0 {
+                        window_context.window().request_redraw();
+                    }
+                }
+                Event::UserEvent(UserEvent::MpvWakeup) => {
+                    while let Some(event) = events.wait_event(0.0) {
+                        match event {
+                            Ok(MpvEvent::EndFile {
+                                reason: mpv_end_file_reason::Error,
+                                error: Some(error),
+                                playlist_entry_id,
+                                ..
+                            }) => {
+                                log::error!(
+                                    "playlist entry {} failed: {}",
+                                    playlist_entry_id,
+                                    error
+                                );
+                            }
+                            Ok(MpvEvent::LogMessage {
+                                prefix,
+                                text,
+                                log_level,
+                                ..
+                            }) => logging::to_log(prefix, log_level, text),
+                            Ok(MpvEvent::Shutdown) => {
+                                *control_flow = ControlFlow::Exit;
+                                break;
+                            }
+                            Ok(_) => (),
+                            Err(err) => log::error!("mpv event failed: {}", err),
+                        }
+                    }
+                }
                 Event::RedrawRequested(_) => unsafe {
                     let window = window_context.window();
 
                     gl_context.clear(glow::COLOR_BUFFER_BIT);
                     gl_context.clear_color(0.2, 0.2, 0.2, 1.0);
 
+                    let size = window.inner_size();
+                    render_context
+                        .render_fbo(0, size.width as _, size.height as _, true)
+                        .unwrap();
+
                     {
                         let frame = imgui.frame();
 
@@ -128,7 +214,6 @@ impl Window {
                             *control_flow = ControlFlow::Exit;
                         }
 
-
                         platform.prepare_render(&frame, window);
                         let draw_data = frame.render();
 
@@ -143,6 +228,7 @@ impl Window {
                     }
 
                     window_context.swap_buffers().unwrap();
+                    render_context.report_swap();
                 },
                 Event::WindowEvent { ref event, .. } => match event {
                     WindowEvent::Resized(physical_size) => unsafe {
@@ -161,5 +247,9 @@ impl Window {
                 }
             }
         });
+
+        // mpv frees its GL resources with the render context, while the GL context is current.
+        drop(render_context);
+        drop(window_context);
     }
 }
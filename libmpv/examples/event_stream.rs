@@ -0,0 +1,37 @@
+//! Play a generated test video with `--vo=null --ao=null`, which needs neither a display nor an
+//! audio device, and print the events of an `EventStream` until the file has ended.
+//!
+//! `cargo run --example event_stream --features futures`
+
+use futures::{executor::block_on, future, StreamExt};
+use libmpv::{events::OwnedEvent, MpvBuilder};
+
+use std::convert::TryFrom;
+
+fn main() -> libmpv::Result<()> {
+    let mpv = MpvBuilder::headless().build()?;
+    let mut events = mpv.create_event_stream(|event| OwnedEvent::try_from(event));
+    let handle = events.handle();
+
+    // Replies are delivered by polling the stream, so requests run alongside it.
+    let requests = async {
+        let version = handle.get_property("mpv-version").await?;
+        println!("{:?}", version);
+        handle
+            .command("loadfile", &["av://lavfi:testsrc=duration=1"])
+            .await
+    };
+    let events = async {
+        while let Some(event) = events.next().await {
+            let event = event?;
+            println!("{:?}", event);
+            if let OwnedEvent::EndFile { .. } = event {
+                break;
+            }
+        }
+        Ok(())
+    };
+
+    let (requests, events) = block_on(future::join(requests, events));
+    requests.and(events)
+}
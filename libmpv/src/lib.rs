@@ -12,6 +12,7 @@ pub use crate::mpv::*;
 /// A format mpv can use.
 pub use libmpv_sys::mpv_format as MpvFormat;
 pub mod mpv_format {
+    pub use libmpv_sys::mpv_format_MPV_FORMAT_BYTE_ARRAY as ByteArray;
     pub use libmpv_sys::mpv_format_MPV_FORMAT_DOUBLE as Double;
     pub use libmpv_sys::mpv_format_MPV_FORMAT_FLAG as Flag;
     pub use libmpv_sys::mpv_format_MPV_FORMAT_INT64 as Int64;
@@ -9,30 +9,80 @@ macro_rules! mpv_cstr_to_str {
     };
 }
 
+mod builder;
+mod client;
 mod errors;
+mod loadfile;
+mod media;
+mod node;
 
 pub mod events;
+pub mod logging;
+pub mod props;
 pub mod protocol;
 pub mod render;
+#[cfg(feature = "futures")]
+pub mod stream;
 
+pub use self::builder::*;
+pub use self::client::*;
 pub use self::errors::*;
+pub use self::loadfile::*;
+pub use self::media::*;
+pub use self::node::*;
 use super::*;
 
 use std::{
     ffi::CString,
     mem::MaybeUninit,
-    ops::Deref,
+    ops::{BitOr, Deref},
     os::raw as ctype,
     ptr::{self, NonNull},
     sync::atomic::AtomicBool,
+    time::Duration,
 };
 
+/// Ids from this value upwards are reserved for the properties observed with
+/// `EventContext::observe_with` and `observe_channel`, and the `reply_userdata` of requests
+/// made through `stream::AsyncMpv`. Ids chosen by the user have to be lower, so that they do
+/// not clash.
+pub const FIRST_RESERVED_ID: u64 = 1 << 63;
+
 fn mpv_err<T>(ret: T, err: ctype::c_int) -> Result<T> {
     if err == 0 {
         Ok(ret)
     } else {
-        Err(Error::Raw(err))
+        Err(Error::from(err))
+    }
+}
+
+/// Convert `value` to an integer, if it has no fractional part and is in range.
+fn f64_to_i64(value: f64) -> Option<i64> {
+    if value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64 {
+        Some(value as i64)
+    } else {
+        None
+    }
+}
+
+// SAFETY: `ba` must be a byte array owned by mpv, which outlives `'a`.
+unsafe fn bytes_from_raw<'a>(ba: &libmpv_sys::mpv_byte_array) -> &'a [u8] {
+    if ba.data.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ba.data as *const u8, ba.size)
+    }
+}
+
+/// Copy a string returned by mpv, and free it. mpv returns null if the call failed, without
+/// telling why.
+fn take_mpv_string(ptr: *mut ctype::c_char) -> Result<String> {
+    if ptr.is_null() {
+        return Err(Error::from(ErrorKind::PropertyUnavailable));
     }
+    let ret = unsafe { mpv_cstr_to_str!(ptr) }.map(str::to_owned);
+    unsafe { libmpv_sys::mpv_free(ptr as *mut _) };
+    ret
 }
 
 /// This trait describes which types are allowed to be passed to getter mpv APIs.
@@ -84,6 +134,7 @@ pub enum MpvNodeValue<'a> {
     Double(f64),
     Array(MpvNodeArrayIter<'a>),
     Map(MpvNodeMapIter<'a>),
+    ByteArray(&'a [u8]),
     None,
 }
 
@@ -94,21 +145,23 @@ pub struct MpvNodeArrayIter<'parent> {
     _does_not_outlive: PhantomData<&'parent MpvNode>,
 }
 
-impl Iterator for MpvNodeArrayIter<'_> {
-    type Item = MpvNode;
+impl<'parent> Iterator for MpvNodeArrayIter<'parent> {
+    type Item = &'parent MpvNode;
 
-    fn next(&mut self) -> Option<MpvNode> {
+    fn next(&mut self) -> Option<&'parent MpvNode> {
         if self.curr >= self.list.num {
             None
         } else {
             let offset = self.curr.try_into().ok()?;
             self.curr += 1;
-            Some(MpvNode(unsafe { *self.list.values.offset(offset) }))
+            // The values are owned by the parent node, so they are only borrowed here.
+            Some(unsafe { &*(self.list.values.offset(offset) as *const MpvNode) })
         }
     }
 }
 
 #[derive(Debug)]
+/// Iterates over the entries of a map node. Entries whose key is not valid UTF-8 are skipped.
 pub struct MpvNodeMapIter<'parent> {
     curr: i32,
     list: libmpv_sys::mpv_node_list,
@@ -116,26 +169,30 @@ pub struct MpvNodeMapIter<'parent> {
 }
 
 impl<'parent> Iterator for MpvNodeMapIter<'parent> {
-    type Item = (&'parent str, MpvNode);
+    type Item = (&'parent str, &'parent MpvNode);
 
-    fn next(&mut self) -> Option<(&'parent str, MpvNode)> {
-        if self.curr >= self.list.num {
-            None
-        } else {
+    fn next(&mut self) -> Option<(&'parent str, &'parent MpvNode)> {
+        while self.curr < self.list.num {
             let offset = self.curr.try_into().ok()?;
             let (key, value) = unsafe {
                 (
                     mpv_cstr_to_str!(*self.list.keys.offset(offset)),
-                    *self.list.values.offset(offset),
+                    &*(self.list.values.offset(offset) as *const MpvNode),
                 )
             };
             self.curr += 1;
-            Some((key.ok()?, MpvNode(value)))
+            if let Ok(key) = key {
+                return Some((key, value));
+            }
         }
+        None
     }
 }
 
+/// A node allocated by mpv, which is freed with mpv's allocator once dropped.
+/// Use `MpvNode::to_node` to get an owned `Node`.
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct MpvNode(libmpv_sys::mpv_node);
 
 impl Drop for MpvNode {
@@ -148,13 +205,22 @@ impl MpvNode {
     pub fn value(&self) -> Result<MpvNodeValue<'_>> {
         let node = self.0;
         Ok(match node.format {
-            mpv_format::Flag => MpvNodeValue::Flag(unsafe { node.u.flag } == 1),
+            mpv_format::Flag => MpvNodeValue::Flag(unsafe { node.u.flag } != 0),
             mpv_format::Int64 => MpvNodeValue::Int64(unsafe { node.u.int64 }),
             mpv_format::Double => MpvNodeValue::Double(unsafe { node.u.double_ }),
             mpv_format::String => {
+                if unsafe { node.u.string }.is_null() {
+                    return Err(Error::Null);
+                }
                 let text = unsafe { mpv_cstr_to_str!(node.u.string) }?;
                 MpvNodeValue::String(text)
             }
+            mpv_format::ByteArray => {
+                if unsafe { node.u.ba }.is_null() {
+                    return Err(Error::Null);
+                }
+                MpvNodeValue::ByteArray(unsafe { bytes_from_raw(&*node.u.ba) })
+            }
 
             mpv_format::Array => MpvNodeValue::Array(MpvNodeArrayIter {
                 list: unsafe { *node.u.list },
@@ -168,7 +234,7 @@ impl MpvNode {
                 _does_not_outlive: PhantomData,
             }),
             mpv_format::None => MpvNodeValue::None,
-            _ => return Err(Error::Raw(mpv_error::PropertyError)),
+            _ => return Err(Error::from(ErrorKind::UnknownFormat)),
         })
     }
 
@@ -179,18 +245,20 @@ impl MpvNode {
             None
         }
     }
+    /// The value as an integer, which may also be a double without a fractional part.
     pub fn to_i64(&self) -> Option<i64> {
-        if let MpvNodeValue::Int64(value) = self.value().ok()? {
-            Some(value)
-        } else {
-            None
+        match self.value().ok()? {
+            MpvNodeValue::Int64(value) => Some(value),
+            MpvNodeValue::Double(value) => f64_to_i64(value),
+            _ => None,
         }
     }
+    /// The value as a double, which may also be an integer.
     pub fn to_f64(&self) -> Option<f64> {
-        if let MpvNodeValue::Double(value) = self.value().ok()? {
-            Some(value)
-        } else {
-            None
+        match self.value().ok()? {
+            MpvNodeValue::Double(value) => Some(value),
+            MpvNodeValue::Int64(value) => Some(value as f64),
+            _ => None,
         }
     }
 
@@ -217,6 +285,14 @@ impl MpvNode {
             None
         }
     }
+
+    pub fn to_bytes(&self) -> Option<&[u8]> {
+        if let MpvNodeValue::ByteArray(value) = self.value().ok()? {
+            Some(value)
+        } else {
+            None
+        }
+    }
 }
 
 unsafe impl GetData for MpvNode {
@@ -328,6 +404,8 @@ unsafe impl<'a> SetData for &'a str {
 /// Subset of `mpv_format` used by the public API.
 pub enum Format {
     String,
+    /// A string formatted for display on the OSD, e.g. `00:01:02` for `time-pos`.
+    OsdString,
     Flag,
     Int64,
     Double,
@@ -338,6 +416,7 @@ impl Format {
     fn as_mpv_format(&self) -> MpvFormat {
         match *self {
             Format::String => mpv_format::String,
+            Format::OsdString => mpv_format::OsdString,
             Format::Flag => mpv_format::Flag,
             Format::Int64 => mpv_format::Int64,
             Format::Double => mpv_format::Double,
@@ -367,6 +446,129 @@ impl FileState {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Where `Mpv::seek` seeks to.
+pub enum SeekTarget {
+    /// Forward from the current position.
+    Relative(Duration),
+    /// Backward from the current position. This is a separate variant, because a `Duration`
+    /// cannot be negative.
+    RelativeBackward(Duration),
+    /// To a position in seconds, which counts from the end if negative.
+    Absolute(f64),
+    /// By a percentage of the duration from the current position, which may be negative.
+    Percent(f64),
+    /// To a percentage of the duration.
+    AbsolutePercent(f64),
+    /// To the start of the chapter with this index.
+    Chapter(i64),
+    /// By a number of frames, which may be negative. A single frame is stepped with `frame-step`
+    /// or `frame-back-step`, which pause playback. Larger counts are an exact seek by
+    /// `frames / container-fps` seconds, which fails with `ErrorKind::PropertyUnavailable` if
+    /// the frame rate is unknown.
+    Frame(i64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// How precisely `Mpv::seek` seeks, which defaults to the `hr-seek` option.
+pub struct SeekFlags(u8);
+
+impl SeekFlags {
+    /// Seek to the exact position, decoding from the previous keyframe.
+    pub const EXACT: SeekFlags = SeekFlags(1);
+    /// Seek to the nearest keyframe, which is faster.
+    pub const KEYFRAMES: SeekFlags = SeekFlags(1 << 1);
+
+    pub fn empty() -> SeekFlags {
+        SeekFlags(0)
+    }
+
+    pub fn contains(self, other: SeekFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for SeekFlags {
+    type Output = SeekFlags;
+
+    fn bitor(self, other: SeekFlags) -> SeekFlags {
+        SeekFlags(self.0 | other.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Which track of a kind is played, as set by the `aid`, `vid`, `sid` and `secondary-sid`
+/// properties.
+pub enum TrackSelection {
+    /// Select the track with this id, as listed in `Mpv::tracks`.
+    TrackId(i64),
+    /// Let mpv's default track selection decide.
+    Auto,
+    /// Disable this kind of track.
+    No,
+}
+
+impl TrackSelection {
+    fn val(&self) -> String {
+        match *self {
+            TrackSelection::TrackId(id) => id.to_string(),
+            TrackSelection::Auto => "auto".to_owned(),
+            TrackSelection::No => "no".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How an external track added with `Mpv::audio_add` is selected.
+pub enum TrackAddFlag {
+    /// Select the track immediately.
+    Select,
+    /// Don't select the track, or let the default track selection decide.
+    Auto,
+    /// Select the track, but if a track with the same file name was already added, select that
+    /// one instead of loading a duplicate.
+    Cached,
+}
+
+impl TrackAddFlag {
+    fn val(&self) -> &str {
+        match *self {
+            TrackAddFlag::Select => "select",
+            TrackAddFlag::Auto => "auto",
+            TrackAddFlag::Cached => "cached",
+        }
+    }
+}
+
+/// A null-terminated argument list as expected by `mpv_command` and friends.
+struct CommandArgs {
+    _strings: Vec<CString>,
+    ptrs: Vec<*const ctype::c_char>,
+}
+
+impl CommandArgs {
+    fn new(args: &[&str]) -> Result<CommandArgs> {
+        let strings = args
+            .iter()
+            .map(|arg| CString::new(*arg))
+            .collect::<::std::result::Result<Vec<_>, _>>()?;
+        let ptrs = strings
+            .iter()
+            .map(|arg| arg.as_ptr())
+            .chain(std::iter::once(ptr::null()))
+            .collect();
+
+        Ok(CommandArgs {
+            _strings: strings,
+            ptrs,
+        })
+    }
+
+    fn as_mut_ptr(&self) -> *mut *const ctype::c_char {
+        self.ptrs.as_ptr() as *mut _
+    }
+}
+
 /// Context passed to the `initializer` of `Mpv::with_initialzer`.
 pub struct MpvInitializer {
     ctx: *mut libmpv_sys::mpv_handle,
@@ -375,22 +577,56 @@ pub struct MpvInitializer {
 impl MpvInitializer {
     /// Set the value of a property.
     pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
         let format = T::get_format().as_mpv_format() as _;
         data.call_as_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_set_property(self.ctx, name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_set_property(self.ctx, c_name.as_ptr(), format, ptr)
             })
         })
+        .map_err(|err| err.in_property(name))
+    }
+
+    /// Set the value of an option, like `--name=value` on the command line.
+    pub fn set_option<T: SetData>(&self, name: &str, data: T) -> Result<()> {
+        set_option(self.ctx, name, data)
+    }
+
+    /// Set an option from its string representation, e.g. a `--name=value` argument passed
+    /// through verbatim.
+    pub fn set_option_string(&self, name: &str, value: &str) -> Result<()> {
+        set_option_string(self.ctx, name, value)
     }
 }
 
+fn set_option<T: SetData>(ctx: *mut libmpv_sys::mpv_handle, name: &str, data: T) -> Result<()> {
+    let c_name = CString::new(name)?;
+    let format = T::get_format().as_mpv_format() as _;
+    data.call_as_c_void(|ptr| {
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_set_option(ctx, c_name.as_ptr(), format, ptr)
+        })
+    })
+    .map_err(|err| err.in_option(name))
+}
+
+fn set_option_string(ctx: *mut libmpv_sys::mpv_handle, name: &str, value: &str) -> Result<()> {
+    let c_name = CString::new(name)?;
+    let c_value = CString::new(value)?;
+    mpv_err((), unsafe {
+        libmpv_sys::mpv_set_option_string(ctx, c_name.as_ptr(), c_value.as_ptr())
+    })
+    .map_err(|err| err.in_option(name))
+}
+
 /// The central mpv context.
 pub struct Mpv {
     /// The handle to the mpv core
     pub ctx: NonNull<libmpv_sys::mpv_handle>,
     events_guard: AtomicBool,
-    protocols_guard: AtomicBool,
+    /// Whether this is a handle created by `create_client` or `create_weak_client`, which
+    /// must not terminate the core when dropped.
+    is_client: bool,
 }
 
 unsafe impl Send for Mpv {}
@@ -399,7 +635,11 @@ unsafe impl Sync for Mpv {}
 impl Drop for Mpv {
     fn drop(&mut self) {
         unsafe {
-            libmpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
+            if self.is_client {
+                libmpv_sys::mpv_destroy(self.ctx.as_ptr());
+            } else {
+                libmpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
+            }
         }
     }
 }
@@ -429,16 +669,17 @@ impl Mpv {
             return Err(Error::Null);
         }
 
-        initializer(MpvInitializer { ctx })?;
-        mpv_err((), unsafe { libmpv_sys::mpv_initialize(ctx) }).map_err(|err| {
+        let result = initializer(MpvInitializer { ctx })
+            .and_then(|()| mpv_err((), unsafe { libmpv_sys::mpv_initialize(ctx) }));
+        if let Err(err) = result {
             unsafe { libmpv_sys::mpv_terminate_destroy(ctx) };
-            err
-        })?;
+            return Err(err);
+        }
 
         Ok(Mpv {
             ctx: unsafe { NonNull::new_unchecked(ctx) },
             events_guard: AtomicBool::new(false),
-            protocols_guard: AtomicBool::new(false),
+            is_client: false,
         })
     }
 
@@ -456,6 +697,7 @@ impl Mpv {
     /// so that the syntax is the same as described in the [manual for the input.conf](https://mpv.io/manual/master/#list-of-input-commands).
     ///
     /// Note that you may have to escape strings with `""` when they contain spaces.
+    /// Use `command_args` to pass arguments verbatim instead.
     pub fn command(&self, name: &str, args: &[&str]) -> Result<()> {
         let mut cmd = name.to_owned();
 
@@ -468,29 +710,188 @@ impl Mpv {
         mpv_err((), unsafe {
             libmpv_sys::mpv_command_string(self.ctx.as_ptr(), raw.as_ptr())
         })
+        .map_err(|err| err.in_command(name))
+    }
+
+    /// Send a command to the `Mpv` instance, where `args[0]` is the name of the command.
+    /// This uses `mpv_command` internally, every argument is passed verbatim, so paths
+    /// containing spaces or quotes need no escaping.
+    pub fn command_args(&self, args: &[&str]) -> Result<()> {
+        let c_args = CommandArgs::new(args)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command(self.ctx.as_ptr(), c_args.as_mut_ptr())
+        })
+        .map_err(|err| match args.first() {
+            // Without arguments there is no command name to report.
+            Some(name) => err.in_command(name),
+            None => err,
+        })
+    }
+
+    /// Like `command_args`, but returns the result of the command, e.g. for `expand-path`
+    /// or `subprocess`. Commands without a result return an `MpvNodeValue::None` node.
+    pub fn command_ret(&self, args: &[&str]) -> Result<MpvNode> {
+        let c_args = CommandArgs::new(args)?;
+        MpvNode::get_from_c_void(|ptr| {
+            mpv_err((), unsafe {
+                libmpv_sys::mpv_command_ret(self.ctx.as_ptr(), c_args.as_mut_ptr(), ptr as *mut _)
+            })
+        })
+        .map_err(|err| match args.first() {
+            // Without arguments there is no command name to report.
+            Some(name) => err.in_command(name),
+            None => err,
+        })
+    }
+
+    /// Send a command given as a node, either an array of arguments or a map with named
+    /// arguments, and return its result. This uses `mpv_command_node` internally.
+    pub fn command_node(&self, args: &Node) -> Result<MpvNode> {
+        let mut raw_args = RawNode::new(args)?;
+        MpvNode::get_from_c_void(|ptr| {
+            mpv_err((), unsafe {
+                libmpv_sys::mpv_command_node(self.ctx.as_ptr(), &mut raw_args.node, ptr as *mut _)
+            })
+        })
+        .map_err(|err| {
+            let name = match args {
+                Node::Array(args) => args.first().and_then(Node::as_str),
+                Node::Map(args) => args.get("name").and_then(Node::as_str),
+                _ => None,
+            };
+            match name {
+                Some(name) => err.in_command(name),
+                None => err,
+            }
+        })
     }
 
     /// Set the value of a property.
     pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
         let format = T::get_format().as_mpv_format() as _;
         data.call_as_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_set_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_set_property(self.ctx.as_ptr(), c_name.as_ptr(), format, ptr)
             })
         })
+        .map_err(|err| err.in_property(name))
     }
 
     /// Get the value of a property.
     pub fn get_property<T: GetData>(&self, name: &str) -> Result<T> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
 
         let format = T::get_format().as_mpv_format() as _;
         T::get_from_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_get_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_get_property(self.ctx.as_ptr(), c_name.as_ptr(), format, ptr)
+            })
+        })
+        .map_err(|err| err.in_property(name))
+    }
+
+    /// Get the value of a property as a string. Unlike `get_property::<String>`, this works
+    /// for every property, e.g. `3.000000` for a double.
+    pub fn get_property_string(&self, name: &str) -> Result<String> {
+        let c_name = CString::new(name)?;
+        let ptr =
+            unsafe { libmpv_sys::mpv_get_property_string(self.ctx.as_ptr(), c_name.as_ptr()) };
+        take_mpv_string(ptr).map_err(|err| err.in_property(name))
+    }
+
+    /// Get the value of a property formatted for display on the OSD, e.g. `00:01:02` for
+    /// `time-pos`.
+    pub fn get_property_osd_string(&self, name: &str) -> Result<String> {
+        let c_name = CString::new(name)?;
+        let ptr =
+            unsafe { libmpv_sys::mpv_get_property_osd_string(self.ctx.as_ptr(), c_name.as_ptr()) };
+        take_mpv_string(ptr).map_err(|err| err.in_property(name))
+    }
+
+    /// Delete a property, e.g. a `user-data` entry. This runs the `del` command, which requires
+    /// mpv 0.35 or newer.
+    pub fn del_property(&self, name: &str) -> Result<()> {
+        let args = CommandArgs::new(&["del", name])?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command(self.ctx.as_ptr(), args.as_mut_ptr())
+        })
+        .map_err(|err| err.in_property(name))
+    }
+
+    /// Set the value of an option at runtime. Most options are also properties, which should
+    /// be preferred, see `set_property`.
+    pub fn set_option<T: SetData>(&self, name: &str, data: T) -> Result<()> {
+        set_option(self.ctx.as_ptr(), name, data)
+    }
+
+    /// Set an option at runtime from its string representation, e.g. a `--name=value` argument
+    /// passed through verbatim.
+    pub fn set_option_string(&self, name: &str, value: &str) -> Result<()> {
+        set_option_string(self.ctx.as_ptr(), name, value)
+    }
+
+    /// Send a command to the `Mpv` instance asynchronously. The result is delivered as an
+    /// `Event::CommandReply` carrying the same `reply_userdata`.
+    ///
+    /// Unlike `command`, every argument is passed verbatim, so no quoting is necessary.
+    pub fn command_async(&self, name: &str, args: &[&str], reply_userdata: u64) -> Result<()> {
+        let args = CommandArgs::new(&[&[name], args].concat())?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command_async(self.ctx.as_ptr(), reply_userdata, args.as_mut_ptr())
+        })
+        .map_err(|err| err.in_command(name))
+    }
+
+    /// Abort a running asynchronous command, started with `command_async`. All commands that
+    /// were started with `reply_userdata` are aborted. Not every command can be aborted,
+    /// and the `Event::CommandReply` is still received.
+    pub fn abort_async_command(&self, reply_userdata: u64) {
+        unsafe { libmpv_sys::mpv_abort_async_command(self.ctx.as_ptr(), reply_userdata) }
+    }
+
+    /// Set the value of a property asynchronously. The result is delivered as an
+    /// `Event::SetPropertyReply` carrying the same `reply_userdata`.
+    pub fn set_property_async<T: SetData>(
+        &self,
+        name: &str,
+        data: T,
+        reply_userdata: u64,
+    ) -> Result<()> {
+        let c_name = CString::new(name)?;
+        let format = T::get_format().as_mpv_format() as _;
+        data.call_as_c_void(|ptr| {
+            mpv_err((), unsafe {
+                libmpv_sys::mpv_set_property_async(
+                    self.ctx.as_ptr(),
+                    reply_userdata,
+                    c_name.as_ptr(),
+                    format,
+                    ptr,
+                )
             })
         })
+        .map_err(|err| err.in_property(name))
+    }
+
+    /// Get the value of a property asynchronously. The value is delivered as an
+    /// `Event::GetPropertyReply` carrying the same `reply_userdata`.
+    pub fn get_property_async(
+        &self,
+        name: &str,
+        format: Format,
+        reply_userdata: u64,
+    ) -> Result<()> {
+        let c_name = CString::new(name)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_get_property_async(
+                self.ctx.as_ptr(),
+                reply_userdata,
+                c_name.as_ptr(),
+                format.as_mpv_format() as _,
+            )
+        })
+        .map_err(|err| err.in_property(name))
     }
 
     /// Internal time in microseconds, this has an arbitrary offset, and will never go backwards.
@@ -505,18 +906,18 @@ impl Mpv {
 
     /// Add -or subtract- any value from a property. Over/underflow clamps to max/min.
     pub fn add_property(&self, property: &str, value: isize) -> Result<()> {
-        self.command("add", &[property, &format!("{}", value)])
+        self.command_args(&["add", property, &format!("{}", value)])
     }
 
     /// Cycle through a given property. `up` specifies direction. On
     /// overflow, set the property back to the minimum, on underflow set it to the maximum.
     pub fn cycle_property(&self, property: &str, up: bool) -> Result<()> {
-        self.command("cycle", &[property, if up { "up" } else { "down" }])
+        self.command_args(&["cycle", property, if up { "up" } else { "down" }])
     }
 
     /// Multiply any property with any positive factor.
     pub fn multiply_property(&self, property: &str, factor: usize) -> Result<()> {
-        self.command("multiply", &[property, &format!("{}", factor)])
+        self.command_args(&["multiply", property, &format!("{}", factor)])
     }
 
     /// Pause playback at runtime.
@@ -532,56 +933,64 @@ impl Mpv {
     // --- Seek functions ---
     //
 
-    /// Seek forward relatively from current position in seconds.
-    /// This is less exact than `seek_absolute`, see [mpv manual]
-    /// (https://mpv.io/manual/master/#command-interface-
-    /// [relative|absolute|absolute-percent|relative-percent|exact|keyframes]).
-    pub fn seek_forward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "relative"])
-    }
-
-    /// See `seek_forward`.
-    pub fn seek_backward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("-{}", secs), "relative"])
-    }
-
-    /// Seek to a given absolute secs.
-    pub fn seek_absolute(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "absolute"])
-    }
-
-    /// Seek to a given relative percent position (may be negative).
-    /// If `percent` of the playtime is bigger than the remaining playtime, the next file is played.
-    /// out of bounds values are clamped to either 0 or 100.
-    pub fn seek_percent(&self, percent: isize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
-    }
-
-    /// Seek to the given percentage of the playtime.
-    pub fn seek_percent_absolute(&self, percent: usize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
+    /// Seek to `target`, and return once mpv has started the seek, see
+    /// [`seek`](https://mpv.io/manual/master/#command-interface-seek).
+    ///
+    /// `flags` are ignored for chapters and frames, and combining `EXACT` with `KEYFRAMES`
+    /// returns an `ErrorKind::InvalidParameter` error.
+    pub fn seek(&self, target: SeekTarget, flags: SeekFlags) -> Result<()> {
+        let precision = match (
+            flags.contains(SeekFlags::EXACT),
+            flags.contains(SeekFlags::KEYFRAMES),
+        ) {
+            (true, true) => return Err(Error::from(ErrorKind::InvalidParameter).in_command("seek")),
+            (true, false) => Some("exact"),
+            (false, true) => Some("keyframes"),
+            (false, false) => None,
+        };
+
+        let (offset, mode) = match target {
+            SeekTarget::Relative(offset) => (offset.as_secs_f64(), "relative"),
+            SeekTarget::RelativeBackward(offset) => (-offset.as_secs_f64(), "relative"),
+            SeekTarget::Absolute(secs) => (secs, "absolute"),
+            SeekTarget::Percent(percent) => (percent, "relative-percent"),
+            SeekTarget::AbsolutePercent(percent) => (percent, "absolute-percent"),
+            SeekTarget::Chapter(chapter) => return self.set_property("chapter", chapter),
+            SeekTarget::Frame(0) => return Ok(()),
+            SeekTarget::Frame(1) => return self.command_args(&["frame-step"]),
+            SeekTarget::Frame(-1) => return self.command_args(&["frame-back-step"]),
+            SeekTarget::Frame(frames) => {
+                let fps: f64 = self.get_property("container-fps")?;
+                if fps <= 0.0 {
+                    let err = Error::from(ErrorKind::PropertyUnavailable);
+                    return Err(err.in_property("container-fps"));
+                }
+                (frames as f64 / fps, "relative")
+            }
+        };
+        // Frames are only reached exactly by an exact seek.
+        let precision = match target {
+            SeekTarget::Frame(_) => Some("exact"),
+            _ => precision,
+        };
+
+        let offset = offset.to_string();
+        match precision {
+            Some(precision) => {
+                self.command_args(&["seek", &offset, &format!("{}+{}", mode, precision)])
+            }
+            None => self.command_args(&["seek", &offset, mode]),
+        }
     }
 
-    /// Revert the previous `seek_` call, can also revert itself.
+    /// Revert the previous `seek` call, can also revert itself.
     pub fn seek_revert(&self) -> Result<()> {
-        self.command("revert-seek", &[])
+        self.command_args(&["revert-seek"])
     }
 
     /// Mark the current position as the position that will be seeked to by `seek_revert`.
     pub fn seek_revert_mark(&self) -> Result<()> {
-        self.command("revert-seek", &["mark"])
-    }
-
-    /// Seek exactly one frame, and pause.
-    /// Noop on audio only streams.
-    pub fn seek_frame(&self) -> Result<()> {
-        self.command("frame-step", &[])
-    }
-
-    /// See `seek_frame`.
-    /// [Note performance considerations.](https://mpv.io/manual/master/#command-interface-frame-back-step)
-    pub fn seek_frame_backward(&self) -> Result<()> {
-        self.command("frame-back-step", &[])
+        self.command_args(&["revert-seek", "mark"])
     }
 
     // --- Screenshot functions ---
@@ -597,9 +1006,9 @@ impl Mpv {
     /// described in [Property Expansion](https://mpv.io/manual/master/#property-expansion)."
     pub fn screenshot_subtitles(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "subtitles"])
+            self.command_args(&["screenshot-to-file", path, "subtitles"])
         } else {
-            self.command("screenshot", &["subtitles"])
+            self.command_args(&["screenshot", "subtitles"])
         }
     }
 
@@ -607,9 +1016,9 @@ impl Mpv {
     /// depends on the selected video output."
     pub fn screenshot_video(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "video"])
+            self.command_args(&["screenshot-to-file", path, "video"])
         } else {
-            self.command("screenshot", &["video"])
+            self.command_args(&["screenshot", "video"])
         }
     }
 
@@ -618,9 +1027,9 @@ impl Mpv {
     /// this will act like video.".
     pub fn screenshot_window(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "window"])
+            self.command_args(&["screenshot-to-file", path, "window"])
         } else {
-            self.command("screenshot", &["window"])
+            self.command_args(&["screenshot", "window"])
         }
     }
 
@@ -630,23 +1039,23 @@ impl Mpv {
     /// Play the next item of the current playlist.
     /// Does nothing if the current item is the last item.
     pub fn playlist_next_weak(&self) -> Result<()> {
-        self.command("playlist-next", &["weak"])
+        self.command_args(&["playlist-next", "weak"])
     }
 
     /// Play the next item of the current playlist.
     /// Terminates playback if the current item is the last item.
     pub fn playlist_next_force(&self) -> Result<()> {
-        self.command("playlist-next", &["force"])
+        self.command_args(&["playlist-next", "force"])
     }
 
     /// See `playlist_next_weak`.
     pub fn playlist_previous_weak(&self) -> Result<()> {
-        self.command("playlist-prev", &["weak"])
+        self.command_args(&["playlist-prev", "weak"])
     }
 
     /// See `playlist_next_force`.
     pub fn playlist_previous_force(&self) -> Result<()> {
-        self.command("playlist-prev", &["force"])
+        self.command_args(&["playlist-prev", "force"])
     }
 
     /// The given files are loaded sequentially, returning the index of the current file
@@ -658,22 +1067,23 @@ impl Mpv {
     ///     * a `FileState` - how the file will be opened
     ///     * an optional string slice - any additional options that will be set for this file
     ///
+    /// See `load_file` to insert files at an index, with typed options, or to get the id of
+    /// their playlist entry.
+    ///
     /// # Peculiarities
     /// `loadfile` is kind of asynchronous, any additional option is set during loading,
     /// [specifics](https://github.com/mpv-player/mpv/issues/4089).
     pub fn playlist_load_files(&self, files: &[(&str, FileState, Option<&str>)]) -> Result<()> {
         for (i, elem) in files.iter().enumerate() {
-            let args = elem.2.unwrap_or("");
-
-            let ret = self.command(
-                "loadfile",
-                &[&format!("\"{}\"", elem.0), elem.1.val(), args],
-            );
+            let ret = match elem.2 {
+                Some(options) => self.command_args(&["loadfile", elem.0, elem.1.val(), options]),
+                None => self.command_args(&["loadfile", elem.0, elem.1.val()]),
+            };
 
             if let Err(err) = ret {
                 return Err(Error::Loadfiles {
                     index: i,
-                    error: ::std::rc::Rc::new(err),
+                    error: ::std::sync::Arc::new(err),
                 });
             }
         }
@@ -683,35 +1093,35 @@ impl Mpv {
     /// Load the given playlist file, that either replaces the current playlist, or appends to it.
     pub fn playlist_load_list(&self, path: &str, replace: bool) -> Result<()> {
         if replace {
-            self.command("loadlist", &[&format!("\"{}\"", path), "replace"])
+            self.command_args(&["loadlist", path, "replace"])
         } else {
-            self.command("loadlist", &[&format!("\"{}\"", path), "append"])
+            self.command_args(&["loadlist", path, "append"])
         }
     }
 
     /// Remove every, except the current, item from the playlist.
     pub fn playlist_clear(&self) -> Result<()> {
-        self.command("playlist-clear", &[])
+        self.command_args(&["playlist-clear"])
     }
 
     /// Remove the currently selected item from the playlist.
     pub fn playlist_remove_current(&self) -> Result<()> {
-        self.command("playlist-remove", &["current"])
+        self.command_args(&["playlist-remove", "current"])
     }
 
     /// Remove item at `position` from the playlist.
     pub fn playlist_remove_index(&self, position: usize) -> Result<()> {
-        self.command("playlist-remove", &[&format!("{}", position)])
+        self.command_args(&["playlist-remove", &format!("{}", position)])
     }
 
     /// Move item `old` to the position of item `new`.
     pub fn playlist_move(&self, old: usize, new: usize) -> Result<()> {
-        self.command("playlist-move", &[&format!("{}", new), &format!("{}", old)])
+        self.command_args(&["playlist-move", &format!("{}", new), &format!("{}", old)])
     }
 
     /// Shuffle the playlist.
     pub fn playlist_shuffle(&self) -> Result<()> {
-        self.command("playlist-shuffle", &[])
+        self.command_args(&["playlist-shuffle"])
     }
 
     // --- Subtitle functions ---
@@ -729,12 +1139,10 @@ impl Mpv {
         lang: Option<&str>,
     ) -> Result<()> {
         match (title, lang) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "select"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "select", t]),
+            (None, None) => self.command_args(&["sub-add", path, "select"]),
+            (Some(t), None) => self.command_args(&["sub-add", path, "select", t]),
             (None, Some(_)) => panic!("Given subtitle language, but missing title"),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "select", t, l])
-            }
+            (Some(t), Some(l)) => self.command_args(&["sub-add", path, "select", t, l]),
         }
     }
 
@@ -752,11 +1160,9 @@ impl Mpv {
         lang: Option<&str>,
     ) -> Result<()> {
         match (title, lang) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto", t]),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "auto", t, l])
-            }
+            (None, None) => self.command_args(&["sub-add", path, "auto"]),
+            (Some(t), None) => self.command_args(&["sub-add", path, "auto", t]),
+            (Some(t), Some(l)) => self.command_args(&["sub-add", path, "auto", t, l]),
             (None, Some(_)) => panic!("Given subtitle language, but missing title"),
         }
     }
@@ -766,16 +1172,16 @@ impl Mpv {
     /// (In this case, title/language are ignored, and if the [sub] was changed since it was loaded,
     /// these changes won't be reflected.)".
     pub fn subtitle_add_cached(&self, path: &str) -> Result<()> {
-        self.command("sub-add", &[&format!("\"{}\"", path), "cached"])
+        self.command_args(&["sub-add", path, "cached"])
     }
 
     /// "Remove the given subtitle track. If the id argument is missing, remove the current
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_remove(&self, index: Option<usize>) -> Result<()> {
         if let Some(idx) = index {
-            self.command("sub-remove", &[&format!("{}", idx)])
+            self.command_args(&["sub-remove", &format!("{}", idx)])
         } else {
-            self.command("sub-remove", &[])
+            self.command_args(&["sub-remove"])
         }
     }
 
@@ -783,16 +1189,16 @@ impl Mpv {
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_reload(&self, index: Option<usize>) -> Result<()> {
         if let Some(idx) = index {
-            self.command("sub-reload", &[&format!("{}", idx)])
+            self.command_args(&["sub-reload", &format!("{}", idx)])
         } else {
-            self.command("sub-reload", &[])
+            self.command_args(&["sub-reload"])
         }
     }
 
     /// "Change subtitle timing such, that the subtitle event after the next `isize` subtitle
     /// events is displayed. `isize` can be negative to step backwards."
     pub fn subtitle_step(&self, skip: isize) -> Result<()> {
-        self.command("sub-step", &[&format!("{}", skip)])
+        self.command_args(&["sub-step", &format!("{}", skip)])
     }
 
     /// "Seek to the next subtitle. This is similar to sub-step, except that it seeks video and
@@ -800,11 +1206,75 @@ impl Mpv {
     /// For embedded subtitles (like with matroska), this works only with subtitle events that
     /// have already been displayed, or are within a short prefetch range."
     pub fn subtitle_seek_forward(&self) -> Result<()> {
-        self.command("sub-seek", &["1"])
+        self.command_args(&["sub-seek", "1"])
     }
 
     /// See `SeekForward`.
     pub fn subtitle_seek_backward(&self) -> Result<()> {
-        self.command("sub-seek", &["-1"])
+        self.command_args(&["sub-seek", "-1"])
+    }
+
+    /// Select the subtitle track, by setting `sid`.
+    pub fn subtitle_select(&self, track: TrackSelection) -> Result<()> {
+        self.set_property("sid", track.val())
+    }
+
+    /// Select the secondary subtitle track, which is displayed in addition to the primary one,
+    /// by setting `secondary-sid`.
+    pub fn subtitle_select_secondary(&self, track: TrackSelection) -> Result<()> {
+        self.set_property("secondary-sid", track.val())
+    }
+
+    // --- Audio and video track functions ---
+    //
+
+    /// Select the audio track, by setting `aid`.
+    pub fn audio_select(&self, track: TrackSelection) -> Result<()> {
+        self.set_property("aid", track.val())
+    }
+
+    /// Select the video track, by setting `vid`.
+    pub fn video_select(&self, track: TrackSelection) -> Result<()> {
+        self.set_property("vid", track.val())
+    }
+
+    /// Load the given external audio file as a track, like `subtitle_add_select` and friends.
+    /// Specifying a language requires specifying a title.
+    ///
+    /// Returns an `ErrorKind::InvalidParameter` error if a language, but not a title, was
+    /// provided.
+    pub fn audio_add(
+        &self,
+        path: &str,
+        flag: TrackAddFlag,
+        title: Option<&str>,
+        lang: Option<&str>,
+    ) -> Result<()> {
+        match (title, lang) {
+            (None, None) => self.command_args(&["audio-add", path, flag.val()]),
+            (Some(t), None) => self.command_args(&["audio-add", path, flag.val(), t]),
+            (Some(t), Some(l)) => self.command_args(&["audio-add", path, flag.val(), t, l]),
+            (None, Some(_)) => Err(Error::from(ErrorKind::InvalidParameter)),
+        }
+    }
+
+    /// "Remove the given audio track. If the id argument is missing, remove the current
+    /// track. (Works on external audio files only.)"
+    pub fn audio_remove(&self, index: Option<usize>) -> Result<()> {
+        if let Some(idx) = index {
+            self.command_args(&["audio-remove", &format!("{}", idx)])
+        } else {
+            self.command_args(&["audio-remove"])
+        }
+    }
+
+    /// "Reload the given audio track. If the id argument is missing, reload the current
+    /// track. (Works on external audio files only.)"
+    pub fn audio_reload(&self, index: Option<usize>) -> Result<()> {
+        if let Some(idx) = index {
+            self.command_args(&["audio-reload", &format!("{}", idx)])
+        } else {
+            self.command_args(&["audio-reload"])
+        }
     }
 }
@@ -0,0 +1,188 @@
+use super::*;
+
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Whether mpv keeps running when there is nothing to play, see the `idle` option.
+pub enum Idle {
+    /// Quit once the playlist has ended.
+    No,
+    /// Wait for new files, even before the first one was loaded.
+    Yes,
+    /// Wait until the first file was loaded, then quit once the playlist has ended.
+    Once,
+}
+
+impl Idle {
+    fn val(&self) -> &str {
+        match *self {
+            Idle::No => "no",
+            Idle::Yes => "yes",
+            Idle::Once => "once",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What happens when a file has ended, see the `keep-open` option.
+pub enum KeepOpen {
+    /// Play the next file, or stop.
+    No,
+    /// Pause on the last frame of the last file in the playlist.
+    Yes,
+    /// Pause on the last frame of every file.
+    Always,
+}
+
+impl KeepOpen {
+    fn val(&self) -> &str {
+        match *self {
+            KeepOpen::No => "no",
+            KeepOpen::Yes => "yes",
+            KeepOpen::Always => "always",
+        }
+    }
+}
+
+/// Builds an `Mpv` with options, which are set before it is initialized.
+///
+/// Setters can be chained, and setting an option twice keeps the last value. Invalid values
+/// are reported by `build`, before mpv is created.
+#[derive(Clone, Debug, Default)]
+pub struct MpvBuilder {
+    options: Vec<(String, String)>,
+    error: Option<Error>,
+}
+
+impl Mpv {
+    /// Create a builder for an `Mpv` with mpv's default options.
+    pub fn builder() -> MpvBuilder {
+        MpvBuilder::new()
+    }
+}
+
+impl MpvBuilder {
+    /// Create a builder with mpv's default options.
+    pub fn new() -> MpvBuilder {
+        MpvBuilder::default()
+    }
+
+    /// Without video or audio output, e.g. for tests or to only read metadata.
+    pub fn headless() -> MpvBuilder {
+        MpvBuilder::new().vo("null").ao("null")
+    }
+
+    /// Rendering video with a `RenderContext`, e.g. into a window of the application.
+    pub fn embedded() -> MpvBuilder {
+        MpvBuilder::new().vo("libmpv")
+    }
+
+    /// Set any option from its string representation, like `--name=value` on the command line.
+    pub fn option(mut self, name: &str, value: &str) -> MpvBuilder {
+        if name.is_empty() || name.contains('\0') || value.contains('\0') {
+            let err = Error::from(ErrorKind::InvalidParameter).in_option(name);
+            self.error.get_or_insert(err);
+        }
+
+        match self.options.iter_mut().find(|(option, _)| option == name) {
+            Some((_, old)) => *old = value.to_owned(),
+            None => self.options.push((name.to_owned(), value.to_owned())),
+        }
+        self
+    }
+
+    fn flag(self, name: &str, value: bool) -> MpvBuilder {
+        self.option(name, if value { "yes" } else { "no" })
+    }
+
+    /// The video output, e.g. `gpu`, `libmpv` or `null`.
+    pub fn vo(self, vo: &str) -> MpvBuilder {
+        self.option("vo", vo)
+    }
+
+    /// The audio output, e.g. `pulse` or `null`.
+    pub fn ao(self, ao: &str) -> MpvBuilder {
+        self.option("ao", ao)
+    }
+
+    /// The hardware decoding API, e.g. `auto`, `vaapi` or `no`.
+    pub fn hwdec(self, hwdec: &str) -> MpvBuilder {
+        self.option("hwdec", hwdec)
+    }
+
+    /// The directory to load `mpv.conf`, scripts and other configuration from, which must exist.
+    /// Only used if `config` is enabled.
+    pub fn config_dir<P: AsRef<Path>>(mut self, path: P) -> MpvBuilder {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            let err = Error::from(ErrorKind::OptionError).in_option("config-dir");
+            self.error.get_or_insert(err);
+        }
+        match path.to_str() {
+            Some(path) => self.option("config-dir", path),
+            None => {
+                self.error.get_or_insert(Error::InvalidUtf8);
+                self
+            }
+        }
+    }
+
+    /// Whether to load the user's configuration files, which libmpv does not by default.
+    pub fn config(self, config: bool) -> MpvBuilder {
+        self.flag("config", config)
+    }
+
+    /// Whether mpv's default key bindings are used.
+    pub fn input_default_bindings(self, enabled: bool) -> MpvBuilder {
+        self.flag("input-default-bindings", enabled)
+    }
+
+    /// Whether mpv prints to the terminal, and reads input from it.
+    pub fn terminal(self, enabled: bool) -> MpvBuilder {
+        self.flag("terminal", enabled)
+    }
+
+    /// The log levels of modules printed to the terminal, e.g. `all=warn,ffmpeg=no`.
+    pub fn msg_level(self, levels: &str) -> MpvBuilder {
+        self.option("msg-level", levels)
+    }
+
+    pub fn idle(self, idle: Idle) -> MpvBuilder {
+        self.option("idle", idle.val())
+    }
+
+    pub fn keep_open(self, keep_open: KeepOpen) -> MpvBuilder {
+        self.option("keep-open", keep_open.val())
+    }
+
+    /// The maximum number of bytes the demuxer caches ahead of the playback position.
+    pub fn demuxer_max_bytes(self, bytes: u64) -> MpvBuilder {
+        self.option("demuxer-max-bytes", &bytes.to_string())
+    }
+
+    /// The maximum number of bytes the demuxer keeps behind the playback position, to allow
+    /// seeking back without reading the file again.
+    pub fn demuxer_max_back_bytes(self, bytes: u64) -> MpvBuilder {
+        self.option("demuxer-max-back-bytes", &bytes.to_string())
+    }
+
+    /// Whether URLs are opened with youtube-dl, which spawns a process for every network URL.
+    pub fn ytdl(self, enabled: bool) -> MpvBuilder {
+        self.flag("ytdl", enabled)
+    }
+
+    /// Create and initialize the `Mpv` with the options set so far.
+    pub fn build(self) -> Result<Mpv> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        let options = self.options;
+        Mpv::with_initializer(|init| {
+            for (name, value) in &options {
+                init.set_option_string(name, value)?;
+            }
+            Ok(())
+        })
+    }
+}
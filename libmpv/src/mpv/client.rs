@@ -0,0 +1,64 @@
+use super::*;
+
+use std::marker::PhantomData;
+
+/// An additional client handle of an mpv core, created with `Mpv::create_client` or
+/// `Mpv::create_weak_client`.
+///
+/// A client has its own event queue and property observers, and derefs to `Mpv` for
+/// everything else. Dropping a client only destroys the handle, not the core, which is
+/// terminated when the `Mpv` it was created from is dropped.
+pub struct Client<'parent> {
+    mpv: Mpv,
+    _does_not_outlive: PhantomData<&'parent Mpv>,
+}
+
+impl<'parent> Deref for Client<'parent> {
+    type Target = Mpv;
+
+    fn deref(&self) -> &Mpv {
+        &self.mpv
+    }
+}
+
+impl Mpv {
+    /// Create a new client handle of the same core, which cannot outlive this `Mpv`.
+    /// `name` is made unique by mpv, see `client_name`.
+    pub fn create_client(&self, name: &str) -> Result<Client> {
+        let name = CString::new(name)?;
+        let ctx = unsafe { libmpv_sys::mpv_create_client(self.ctx.as_ptr(), name.as_ptr()) };
+        Client::from_raw(ctx)
+    }
+
+    /// Like `create_client`, but the handle is weak, i.e. it does not count as a reason to
+    /// keep the core running, e.g. for scripts that check which clients are left.
+    pub fn create_weak_client(&self, name: &str) -> Result<Client> {
+        let name = CString::new(name)?;
+        let ctx = unsafe { libmpv_sys::mpv_create_weak_client(self.ctx.as_ptr(), name.as_ptr()) };
+        Client::from_raw(ctx)
+    }
+
+    /// The name of this client handle, e.g. `main` for the handle returned by `Mpv::new`.
+    pub fn client_name(&self) -> Result<&str> {
+        unsafe { mpv_cstr_to_str!(libmpv_sys::mpv_client_name(self.ctx.as_ptr())) }
+    }
+
+    /// The id of this client handle, which is unique within the core.
+    pub fn client_id(&self) -> i64 {
+        unsafe { libmpv_sys::mpv_client_id(self.ctx.as_ptr()) }
+    }
+}
+
+impl<'parent> Client<'parent> {
+    fn from_raw(ctx: *mut libmpv_sys::mpv_handle) -> Result<Client<'parent>> {
+        let ctx = NonNull::new(ctx).ok_or(Error::Null)?;
+        Ok(Client {
+            mpv: Mpv {
+                ctx,
+                events_guard: AtomicBool::new(false),
+                is_client: true,
+            },
+            _does_not_outlive: PhantomData,
+        })
+    }
+}
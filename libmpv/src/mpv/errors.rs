@@ -1,13 +1,130 @@
-use std::{error, ffi::NulError, fmt, os::raw as ctype, rc::Rc, str::Utf8Error};
+use std::{error, ffi::NulError, fmt, os::raw as ctype, str::Utf8Error, sync::Arc};
+
+use crate::{mpv_error, MpvError};
 
 #[allow(missing_docs)]
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// The category of an error returned by mpv, one for each `mpv_error` code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    EventQueueFull,
+    NoMem,
+    Uninitialized,
+    InvalidParameter,
+    OptionNotFound,
+    OptionFormat,
+    OptionError,
+    PropertyNotFound,
+    PropertyFormat,
+    PropertyUnavailable,
+    PropertyError,
+    Command,
+    LoadingFailed,
+    AoInitFailed,
+    VoInitFailed,
+    NothingToPlay,
+    UnknownFormat,
+    Unsupported,
+    NotImplemented,
+    Generic,
+    /// An error code that is not known to these bindings.
+    Other(MpvError),
+}
+
+impl ErrorKind {
+    /// The kind of the (negative) error `code`.
+    pub fn from_code(code: MpvError) -> ErrorKind {
+        match code {
+            mpv_error::EventQueueFull => ErrorKind::EventQueueFull,
+            mpv_error::NoMem => ErrorKind::NoMem,
+            mpv_error::Uninitialized => ErrorKind::Uninitialized,
+            mpv_error::InvalidParameter => ErrorKind::InvalidParameter,
+            mpv_error::OptionNotFound => ErrorKind::OptionNotFound,
+            mpv_error::OptionFormat => ErrorKind::OptionFormat,
+            mpv_error::OptionError => ErrorKind::OptionError,
+            mpv_error::PropertyNotFound => ErrorKind::PropertyNotFound,
+            mpv_error::PropertyFormat => ErrorKind::PropertyFormat,
+            mpv_error::PropertyUnavailable => ErrorKind::PropertyUnavailable,
+            mpv_error::PropertyError => ErrorKind::PropertyError,
+            mpv_error::Command => ErrorKind::Command,
+            mpv_error::LoadingFailed => ErrorKind::LoadingFailed,
+            mpv_error::AoInitFailed => ErrorKind::AoInitFailed,
+            mpv_error::VoInitFailed => ErrorKind::VoInitFailed,
+            mpv_error::NothingToPlay => ErrorKind::NothingToPlay,
+            mpv_error::UnknownFormat => ErrorKind::UnknownFormat,
+            mpv_error::Unsupported => ErrorKind::Unsupported,
+            mpv_error::NotImplemented => ErrorKind::NotImplemented,
+            mpv_error::Generic => ErrorKind::Generic,
+            code => ErrorKind::Other(code),
+        }
+    }
+
+    /// The `mpv_error` code of this kind.
+    pub fn code(self) -> MpvError {
+        match self {
+            ErrorKind::EventQueueFull => mpv_error::EventQueueFull,
+            ErrorKind::NoMem => mpv_error::NoMem,
+            ErrorKind::Uninitialized => mpv_error::Uninitialized,
+            ErrorKind::InvalidParameter => mpv_error::InvalidParameter,
+            ErrorKind::OptionNotFound => mpv_error::OptionNotFound,
+            ErrorKind::OptionFormat => mpv_error::OptionFormat,
+            ErrorKind::OptionError => mpv_error::OptionError,
+            ErrorKind::PropertyNotFound => mpv_error::PropertyNotFound,
+            ErrorKind::PropertyFormat => mpv_error::PropertyFormat,
+            ErrorKind::PropertyUnavailable => mpv_error::PropertyUnavailable,
+            ErrorKind::PropertyError => mpv_error::PropertyError,
+            ErrorKind::Command => mpv_error::Command,
+            ErrorKind::LoadingFailed => mpv_error::LoadingFailed,
+            ErrorKind::AoInitFailed => mpv_error::AoInitFailed,
+            ErrorKind::VoInitFailed => mpv_error::VoInitFailed,
+            ErrorKind::NothingToPlay => mpv_error::NothingToPlay,
+            ErrorKind::UnknownFormat => mpv_error::UnknownFormat,
+            ErrorKind::Unsupported => mpv_error::Unsupported,
+            ErrorKind::NotImplemented => mpv_error::NotImplemented,
+            ErrorKind::Generic => mpv_error::Generic,
+            ErrorKind::Other(code) => code,
+        }
+    }
+
+    /// mpv's description of this kind, e.g. `property unavailable`.
+    pub fn as_str(self) -> &'static str {
+        libmpv_sys::mpv_error_str(self.code())
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What mpv was asked to do when an error occurred.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorContext {
+    /// The command with this name failed.
+    Command(String),
+    /// Accessing the property with this name failed.
+    Property(String),
+    /// Setting the option with this name failed.
+    Option(String),
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorContext::Command(name) => write!(f, "command `{}`", name),
+            ErrorContext::Property(name) => write!(f, "property `{}`", name),
+            ErrorContext::Option(name) => write!(f, "option `{}`", name),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Error {
     Loadfiles {
         index: usize,
-        error: Rc<Error>,
+        error: Arc<Error>,
     },
     VersionMismatch {
         linked: ctype::c_ulong,
@@ -15,12 +132,84 @@ pub enum Error {
     },
     InvalidUtf8,
     Null,
-    Raw(crate::MpvError),
+    /// A `Node` could not be decoded into the requested type.
+    Deserialize(String),
+    /// mpv returned an error, optionally with the command or property that failed.
+    Mpv {
+        kind: ErrorKind,
+        context: Option<ErrorContext>,
+    },
+}
+
+// `Error` is passed between threads, e.g. by `anyhow`.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<Error>();
+};
+
+impl Error {
+    /// The kind of the error returned by mpv, if any.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Error::Mpv { kind, .. } => Some(*kind),
+            Error::Loadfiles { error, .. } => error.kind(),
+            _ => None,
+        }
+    }
+
+    /// Attach `context` to an error returned by mpv, unless it already has one.
+    pub(crate) fn with_context(self, context: ErrorContext) -> Error {
+        match self {
+            Error::Mpv {
+                kind,
+                context: None,
+            } => Error::Mpv {
+                kind,
+                context: Some(context),
+            },
+            err => err,
+        }
+    }
+
+    pub(crate) fn in_command(self, name: &str) -> Error {
+        self.with_context(ErrorContext::Command(name.to_owned()))
+    }
+
+    pub(crate) fn in_property(self, name: &str) -> Error {
+        self.with_context(ErrorContext::Property(name.to_owned()))
+    }
+
+    pub(crate) fn in_option(self, name: &str) -> Error {
+        self.with_context(ErrorContext::Option(name.to_owned()))
+    }
 }
 
 impl fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "{:?}", self)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Loadfiles { index, error } => {
+                write!(f, "failed to load file {}: {}", index, error)
+            }
+            Error::VersionMismatch { linked, loaded } => write!(
+                f,
+                "mpv client API version mismatch: linked against {}.{}, loaded {}.{}",
+                linked >> 16,
+                linked & 0xffff,
+                loaded >> 16,
+                loaded & 0xffff
+            ),
+            Error::InvalidUtf8 => f.write_str("invalid UTF-8 in a string"),
+            Error::Null => f.write_str("unexpected null pointer or nul byte in a string"),
+            Error::Deserialize(message) => write!(f, "failed to decode node: {}", message),
+            Error::Mpv {
+                kind,
+                context: Some(context),
+            } => write!(f, "{}: {}", context, kind),
+            Error::Mpv {
+                kind,
+                context: None,
+            } => write!(f, "{}", kind),
+        }
     }
 }
 
@@ -35,9 +224,19 @@ impl From<Utf8Error> for Error {
         Error::InvalidUtf8
     }
 }
-impl From<crate::MpvError> for Error {
-    fn from(other: crate::MpvError) -> Error {
-        Error::Raw(other)
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::Mpv {
+            kind,
+            context: None,
+        }
+    }
+}
+
+impl From<MpvError> for Error {
+    fn from(code: MpvError) -> Error {
+        ErrorKind::from_code(code).into()
     }
 }
 
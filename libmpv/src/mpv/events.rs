@@ -1,13 +1,23 @@
 use libmpv_sys::mpv_event;
 
-use crate::{mpv::mpv_err, *};
+use crate::{
+    mpv::{bytes_from_raw, f64_to_i64, mpv_err},
+    *,
+};
 
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
 use std::ffi::CString;
 use std::marker::PhantomData;
+use std::mem;
 use std::os::raw as ctype;
-use std::ptr::NonNull;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
 use std::slice;
 use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc, Mutex, PoisonError, Weak};
 
 /// An `Event`'s ID.
 pub use libmpv_sys::mpv_event_id as EventId;
@@ -33,6 +43,13 @@ pub mod mpv_event_id {
 }
 
 impl Mpv {
+    /// Continue a hook, which was received as an `OwnedEvent::Hook` with this `id`.
+    pub fn continue_hook(&self, id: u64) -> Result<()> {
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_hook_continue(self.ctx.as_ptr(), id)
+        })
+    }
+
     /// Create a context that can be used to wait for events and control which events are listened
     /// for.
     ///
@@ -47,6 +64,9 @@ impl Mpv {
         } else {
             EventContext {
                 ctx: self.ctx,
+                wakeup_callback: None,
+                observers: Arc::new(Mutex::new(HashMap::new())),
+                next_observer_id: FIRST_RESERVED_ID,
                 _does_not_outlive: PhantomData::<&Self>,
             }
         }
@@ -62,37 +82,265 @@ pub enum PropertyData<'a> {
     Int64(i64),
     Double(ctype::c_double),
     Node(&'a MpvNode),
+    ByteArray(&'a [u8]),
+    /// The property is not available, e.g. `duration` once the file has ended.
+    Unavailable,
 }
 
 impl<'a> PropertyData<'a> {
     // SAFETY: meant to extract the data from an event property. See `mpv_event_property` in
     // `client.h`
     unsafe fn from_raw(format: MpvFormat, ptr: *mut ctype::c_void) -> Result<PropertyData<'a>> {
-        assert!(!ptr.is_null());
+        if format == mpv_format::None {
+            return Ok(PropertyData::Unavailable);
+        }
+        if ptr.is_null() {
+            return Err(Error::Null);
+        }
         match format {
-            mpv_format::Flag => Ok(PropertyData::Flag(*(ptr as *mut bool))),
-            mpv_format::String => {
-                let char_ptr = *(ptr as *mut *mut ctype::c_char);
-                Ok(PropertyData::Str(mpv_cstr_to_str!(char_ptr)?))
-            }
-            mpv_format::OsdString => {
-                let char_ptr = *(ptr as *mut *mut ctype::c_char);
-                Ok(PropertyData::OsdStr(mpv_cstr_to_str!(char_ptr)?))
-            }
+            // Flags are C ints, not `bool`s.
+            mpv_format::Flag => Ok(PropertyData::Flag(*(ptr as *mut ctype::c_int) != 0)),
+            mpv_format::String => Ok(PropertyData::Str(str_from_raw(ptr)?)),
+            mpv_format::OsdString => Ok(PropertyData::OsdStr(str_from_raw(ptr)?)),
             mpv_format::Double => Ok(PropertyData::Double(*(ptr as *mut f64))),
             mpv_format::Int64 => Ok(PropertyData::Int64(*(ptr as *mut i64))),
             mpv_format::Node => Ok(PropertyData::Node(&*(ptr as *mut MpvNode))),
-            mpv_format::None => unreachable!(),
-            _ => unimplemented!(),
+            mpv_format::ByteArray => {
+                let ba = &*(ptr as *mut libmpv_sys::mpv_byte_array);
+                Ok(PropertyData::ByteArray(bytes_from_raw(ba)))
+            }
+            _ => Err(Error::from(ErrorKind::UnknownFormat)),
+        }
+    }
+
+    /// The value as a string, which may be an OSD string.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            PropertyData::Str(value) | PropertyData::OsdStr(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PropertyData::Flag(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, which may also be a double without a fractional part.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            PropertyData::Int64(value) => Some(value),
+            PropertyData::Double(value) => f64_to_i64(value),
+            _ => None,
+        }
+    }
+
+    /// The value as a double, which may also be an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PropertyData::Double(value) => Some(value),
+            PropertyData::Int64(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            PropertyData::ByteArray(value) => Some(value),
+            PropertyData::Node(node) => node.to_bytes(),
+            _ => None,
+        }
+    }
+}
+
+// SAFETY: `ptr` must point to a string pointer, which may be null.
+unsafe fn str_from_raw<'a>(ptr: *mut ctype::c_void) -> Result<&'a str> {
+    let char_ptr = *(ptr as *mut *mut ctype::c_char);
+    if char_ptr.is_null() {
+        return Err(Error::Null);
+    }
+    mpv_cstr_to_str!(char_ptr)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An owned version of `PropertyData`.
+pub enum OwnedPropertyData {
+    Str(String),
+    OsdStr(String),
+    Flag(bool),
+    Int64(i64),
+    Double(ctype::c_double),
+    Node(Node),
+    ByteArray(Vec<u8>),
+    /// The property is not available, e.g. `duration` once the file has ended.
+    Unavailable,
+}
+
+impl OwnedPropertyData {
+    /// The value as a string, which may be an OSD string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OwnedPropertyData::Str(value) | OwnedPropertyData::OsdStr(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            OwnedPropertyData::Flag(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, which may also be a double without a fractional part.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            OwnedPropertyData::Int64(value) => Some(value),
+            OwnedPropertyData::Double(value) => f64_to_i64(value),
+            _ => None,
+        }
+    }
+
+    /// The value as a double, which may also be an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OwnedPropertyData::Double(value) => Some(value),
+            OwnedPropertyData::Int64(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            OwnedPropertyData::ByteArray(value) => Some(value),
+            OwnedPropertyData::Node(node) => node.as_bytes(),
+            _ => None,
+        }
+    }
+}
+
+/// Fails with `Error::InvalidUtf8` if a node contains a string that is not valid UTF-8.
+impl<'a> TryFrom<PropertyData<'a>> for OwnedPropertyData {
+    type Error = Error;
+
+    fn try_from(data: PropertyData<'a>) -> Result<OwnedPropertyData> {
+        Ok(match data {
+            PropertyData::Str(value) => OwnedPropertyData::Str(value.to_owned()),
+            PropertyData::OsdStr(value) => OwnedPropertyData::OsdStr(value.to_owned()),
+            PropertyData::Flag(value) => OwnedPropertyData::Flag(value),
+            PropertyData::Int64(value) => OwnedPropertyData::Int64(value),
+            PropertyData::Double(value) => OwnedPropertyData::Double(value),
+            PropertyData::Node(node) => OwnedPropertyData::Node(node.to_node()?),
+            PropertyData::ByteArray(value) => OwnedPropertyData::ByteArray(value.to_vec()),
+            PropertyData::Unavailable => OwnedPropertyData::Unavailable,
+        })
+    }
+}
+
+/// Types that can be decoded from the `PropertyData` of a change to a property, that was observed
+/// in the format given by `GetData::get_format`.
+pub trait FromPropertyData: GetData {
+    /// Decode `data`, or return `None` if it is unavailable or has a different format.
+    fn from_property_data(data: &PropertyData) -> Option<Self>;
+}
+
+impl FromPropertyData for f64 {
+    fn from_property_data(data: &PropertyData) -> Option<f64> {
+        match *data {
+            PropertyData::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyData for Vec<u8> {
+    fn from_property_data(data: &PropertyData) -> Option<Vec<u8>> {
+        data.as_bytes().map(<[u8]>::to_vec)
+    }
+}
+
+impl FromPropertyData for i64 {
+    fn from_property_data(data: &PropertyData) -> Option<i64> {
+        match *data {
+            PropertyData::Int64(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyData for bool {
+    fn from_property_data(data: &PropertyData) -> Option<bool> {
+        match *data {
+            PropertyData::Flag(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyData for String {
+    fn from_property_data(data: &PropertyData) -> Option<String> {
+        match *data {
+            PropertyData::Str(value) | PropertyData::OsdStr(value) => Some(value.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyData for Node {
+    fn from_property_data(data: &PropertyData) -> Option<Node> {
+        match *data {
+            PropertyData::Node(node) => node.to_node().ok(),
+            _ => None,
         }
     }
 }
 
+/// A running hook, as received with `Event::Hook`. mpv is blocked until the hook is continued,
+/// which happens when this is dropped, or explicitly with `continue_hook`.
+#[derive(Debug)]
+pub struct HookId<'a> {
+    ctx: NonNull<libmpv_sys::mpv_handle>,
+    id: u64,
+    _does_not_outlive: PhantomData<&'a Mpv>,
+}
+
+unsafe impl<'a> Send for HookId<'a> {}
+
+impl<'a> HookId<'a> {
+    /// The internal id of the hook, as passed to `mpv_hook_continue`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Take the internal id without continuing the hook, which then has to be continued with
+    /// `Mpv::continue_hook`.
+    pub fn into_id(self) -> u64 {
+        let id = self.id;
+        mem::forget(self);
+        id
+    }
+
+    /// Let mpv continue, after the hook was handled.
+    pub fn continue_hook(self) -> Result<()> {
+        let ret = unsafe { libmpv_sys::mpv_hook_continue(self.ctx.as_ptr(), self.id) };
+        mem::forget(self);
+        mpv_err((), ret)
+    }
+}
+
+impl<'a> Drop for HookId<'a> {
+    fn drop(&mut self) {
+        unsafe { libmpv_sys::mpv_hook_continue(self.ctx.as_ptr(), self.id) };
+    }
+}
+
 #[derive(Debug)]
 pub enum Event<'a> {
     /// Received when the player is shutting down
     Shutdown,
-    /// *Has not been tested*, received when explicitly asked to MPV
+    /// Received when log messages were requested with `Mpv::request_log_messages`
     LogMessage {
         prefix: &'a str,
         level: &'a str,
@@ -110,9 +358,23 @@ pub enum Event<'a> {
     /// Received when using command_async
     CommandReply(u64),
     /// Event received when a new file is playing
-    StartFile,
+    StartFile {
+        /// The id of the playlist entry that is being started.
+        playlist_entry_id: i64,
+    },
     /// Event received when the file being played currently has stopped, for an error or not
-    EndFile(EndFileReason),
+    EndFile {
+        reason: EndFileReason,
+        /// Why playback failed, if `reason` is `mpv_end_file_reason::Error`.
+        error: Option<Error>,
+        /// The id of the playlist entry that ended.
+        playlist_entry_id: i64,
+        /// If `reason` is `mpv_end_file_reason::Redirect`, the id of the first playlist entry
+        /// that was inserted in place of the ended one, or `0`.
+        playlist_insert_id: i64,
+        /// If `reason` is `mpv_end_file_reason::Redirect`, the number of inserted entries.
+        playlist_insert_num_entries: i32,
+    },
     /// Event received when a file has been *loaded*, but has not been started
     FileLoaded,
     ClientMessage(Vec<&'a str>),
@@ -129,18 +391,205 @@ pub enum Event<'a> {
     },
     /// Received when the Event Queue is full
     QueueOverflow,
+    /// Received when a hook registered with `add_hook` is run. mpv is blocked until `id` is
+    /// continued or dropped.
+    Hook {
+        name: &'a str,
+        reply_userdata: u64,
+        id: HookId<'a>,
+    },
     /// A deprecated event
     Deprecated(mpv_event),
 }
 
+#[derive(Clone, Debug)]
+/// An owned version of `Event`, which does not borrow from the `EventContext`, and can be stored
+/// or sent to other threads.
+pub enum OwnedEvent {
+    Shutdown,
+    LogMessage {
+        prefix: String,
+        level: String,
+        text: String,
+        log_level: LogLevel,
+    },
+    GetPropertyReply {
+        name: String,
+        result: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    SetPropertyReply(u64),
+    CommandReply(u64),
+    StartFile {
+        playlist_entry_id: i64,
+    },
+    EndFile {
+        reason: EndFileReason,
+        error: Option<Error>,
+        playlist_entry_id: i64,
+        playlist_insert_id: i64,
+        playlist_insert_num_entries: i32,
+    },
+    FileLoaded,
+    ClientMessage(Vec<String>),
+    VideoReconfig,
+    AudioReconfig,
+    Seek,
+    PlaybackRestart,
+    PropertyChange {
+        name: String,
+        change: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    QueueOverflow,
+    /// A hook that was run. Unlike `Event::Hook`, it is not continued automatically, and has to
+    /// be continued with `Mpv::continue_hook(id)`.
+    Hook {
+        name: String,
+        reply_userdata: u64,
+        id: u64,
+    },
+    /// A deprecated event
+    Deprecated(EventId),
+}
+
+/// Fails if the property data of the event cannot be converted, see `OwnedPropertyData`.
+impl<'a> TryFrom<Event<'a>> for OwnedEvent {
+    type Error = Error;
+
+    fn try_from(event: Event<'a>) -> Result<OwnedEvent> {
+        Ok(match event {
+            Event::Shutdown => OwnedEvent::Shutdown,
+            Event::LogMessage {
+                prefix,
+                level,
+                text,
+                log_level,
+            } => OwnedEvent::LogMessage {
+                prefix: prefix.to_owned(),
+                level: level.to_owned(),
+                text: text.to_owned(),
+                log_level,
+            },
+            Event::GetPropertyReply {
+                name,
+                result,
+                reply_userdata,
+            } => OwnedEvent::GetPropertyReply {
+                name: name.to_owned(),
+                result: result.try_into()?,
+                reply_userdata,
+            },
+            Event::SetPropertyReply(reply_userdata) => OwnedEvent::SetPropertyReply(reply_userdata),
+            Event::CommandReply(reply_userdata) => OwnedEvent::CommandReply(reply_userdata),
+            Event::StartFile { playlist_entry_id } => OwnedEvent::StartFile { playlist_entry_id },
+            Event::EndFile {
+                reason,
+                error,
+                playlist_entry_id,
+                playlist_insert_id,
+                playlist_insert_num_entries,
+            } => OwnedEvent::EndFile {
+                reason,
+                error,
+                playlist_entry_id,
+                playlist_insert_id,
+                playlist_insert_num_entries,
+            },
+            Event::FileLoaded => OwnedEvent::FileLoaded,
+            Event::ClientMessage(messages) => {
+                OwnedEvent::ClientMessage(messages.into_iter().map(str::to_owned).collect())
+            }
+            Event::VideoReconfig => OwnedEvent::VideoReconfig,
+            Event::AudioReconfig => OwnedEvent::AudioReconfig,
+            Event::Seek => OwnedEvent::Seek,
+            Event::PlaybackRestart => OwnedEvent::PlaybackRestart,
+            Event::PropertyChange {
+                name,
+                change,
+                reply_userdata,
+            } => OwnedEvent::PropertyChange {
+                name: name.to_owned(),
+                change: change.try_into()?,
+                reply_userdata,
+            },
+            Event::QueueOverflow => OwnedEvent::QueueOverflow,
+            Event::Hook {
+                name,
+                reply_userdata,
+                id,
+            } => OwnedEvent::Hook {
+                name: name.to_owned(),
+                reply_userdata,
+                id: id.into_id(),
+            },
+            Event::Deprecated(event) => OwnedEvent::Deprecated(event.event_id),
+        })
+    }
+}
+
 /// Context to listen to events.
 pub struct EventContext<'parent> {
     ctx: NonNull<libmpv_sys::mpv_handle>,
+    wakeup_callback: Option<Box<WakeupCallback>>,
+    observers: Arc<Mutex<HashMap<u64, ObserverCallback>>>,
+    next_observer_id: u64,
     _does_not_outlive: PhantomData<&'parent Mpv>,
 }
 
+// Callbacks are cloned out of the map before being called, so that they can drop handles.
+type ObserverCallback = Arc<Mutex<dyn FnMut(&PropertyData) + Send>>;
+
+/// A property observer registered with `EventContext::observe_with` or
+/// `EventContext::observe_channel`. The property is unobserved once this is dropped.
+pub struct ObserverHandle<'parent> {
+    ctx: NonNull<libmpv_sys::mpv_handle>,
+    id: u64,
+    observers: Weak<Mutex<HashMap<u64, ObserverCallback>>>,
+    _does_not_outlive: PhantomData<&'parent Mpv>,
+}
+
+unsafe impl<'parent> Send for ObserverHandle<'parent> {}
+
+impl<'parent> ObserverHandle<'parent> {
+    /// The id the property is observed with, as found in `Event::PropertyChange`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<'parent> Drop for ObserverHandle<'parent> {
+    fn drop(&mut self) {
+        unsafe { libmpv_sys::mpv_unobserve_property(self.ctx.as_ptr(), self.id) };
+        if let Some(observers) = self.observers.upgrade() {
+            observers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&self.id);
+        }
+    }
+}
+
 unsafe impl<'parent> Send for EventContext<'parent> {}
 
+type WakeupCallback = Box<dyn Fn() + Send>;
+
+unsafe extern "C" fn wakeup_callback_wrapper(cb_ctx: *mut ctype::c_void) {
+    let callback = &*(cb_ctx as *const WakeupCallback);
+    let _ = panic::catch_unwind(AssertUnwindSafe(callback));
+}
+
+impl<'parent> Drop for EventContext<'parent> {
+    fn drop(&mut self) {
+        // Unset the callback before it is freed, mpv may call it until then.
+        if self.wakeup_callback.is_some() {
+            unsafe {
+                libmpv_sys::mpv_set_wakeup_callback(self.ctx.as_ptr(), None, ptr::null_mut())
+            };
+        }
+    }
+}
+
 impl<'parent> EventContext<'parent> {
     /// Enable an event.
     pub fn enable_event(&self, ev: events::EventId) -> Result<()> {
@@ -193,15 +642,16 @@ impl<'parent> EventContext<'parent> {
     /// Observe `name` property for changes. `id` can be used to unobserve this (or many) properties
     /// again.
     pub fn observe_property(&self, name: &str, format: Format, id: u64) -> Result<()> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
         mpv_err((), unsafe {
             libmpv_sys::mpv_observe_property(
                 self.ctx.as_ptr(),
                 id,
-                name.as_ptr(),
+                c_name.as_ptr(),
                 format.as_mpv_format() as _,
             )
         })
+        .map_err(|err| err.in_property(name))
     }
 
     /// Unobserve any property associated with `id`.
@@ -211,6 +661,103 @@ impl<'parent> EventContext<'parent> {
         })
     }
 
+    /// Register a hook handler for the hook `name`, e.g. `on_load`, `on_preloaded` or `on_unload`.
+    /// Whenever the hook is run, an `Event::Hook` with `id` as `reply_userdata` is received.
+    /// Handlers with a lower `priority` are run first, `0` is a neutral priority.
+    ///
+    /// See the [hooks](https://mpv.io/manual/master/#hooks) section of the mpv manual.
+    pub fn add_hook(&self, name: &str, priority: i32, id: u64) -> Result<()> {
+        let name = CString::new(name)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_hook_add(self.ctx.as_ptr(), id, name.as_ptr(), priority)
+        })
+    }
+
+    /// Observe the typed property `P`, and call `callback` with its new value on every change,
+    /// or with `None` if it became unavailable. Callbacks are called from `wait_event`, before the
+    /// `Event::PropertyChange` is returned.
+    pub fn observe_with<P, F>(&mut self, mut callback: F) -> Result<ObserverHandle<'parent>>
+    where
+        P: props::Property,
+        P::Value: FromPropertyData,
+        F: FnMut(Option<P::Value>) + Send + 'static,
+    {
+        let id = self.next_observer_id;
+        self.next_observer_id += 1;
+
+        self.observers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                id,
+                Arc::new(Mutex::new(move |data: &PropertyData| {
+                    callback(P::Value::from_property_data(data))
+                })),
+            );
+        if let Err(err) = self.observe::<P>(id) {
+            self.observers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&id);
+            return Err(err);
+        }
+
+        Ok(ObserverHandle {
+            ctx: self.ctx,
+            id,
+            observers: Arc::downgrade(&self.observers),
+            _does_not_outlive: PhantomData,
+        })
+    }
+
+    /// Like `observe_with`, but send the new values to the returned channel.
+    pub fn observe_channel<P>(
+        &mut self,
+    ) -> Result<(ObserverHandle<'parent>, mpsc::Receiver<Option<P::Value>>)>
+    where
+        P: props::Property,
+        P::Value: FromPropertyData + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.observe_with::<P, _>(move |value| {
+            // The receiver may have been dropped before the handle.
+            let _ = sender.send(value);
+        })?;
+        Ok((handle, receiver))
+    }
+
+    /// Set the `callback` that is called when new events are available, replacing any previous
+    /// one. This allows driving `wait_event` with a timeout of `0` from another event loop.
+    ///
+    /// The callback may be called from any thread. It must not call any mpv API, and should
+    /// only notify the thread calling `wait_event`, e.g. through an `EventLoopProxy` or a channel.
+    pub fn set_wakeup_callback<F: Fn() + Send + 'static>(&mut self, callback: F) {
+        let callback: Box<WakeupCallback> = Box::new(Box::new(callback));
+        unsafe {
+            libmpv_sys::mpv_set_wakeup_callback(
+                self.ctx.as_ptr(),
+                Some(wakeup_callback_wrapper),
+                &*callback as *const WakeupCallback as *mut _,
+            )
+        };
+        self.wakeup_callback = Some(callback);
+    }
+
+    /// Get a pipe that becomes readable when new events are available, for use with `poll` or
+    /// `epoll`. Read and discard all data from it before calling `wait_event`.
+    ///
+    /// The pipe is owned by mpv, and must not be closed. Returns an `ErrorKind::Generic` error
+    /// if it could not be created.
+    #[cfg(unix)]
+    pub fn wakeup_pipe(&self) -> Result<RawFd> {
+        let fd = unsafe { libmpv_sys::mpv_get_wakeup_pipe(self.ctx.as_ptr()) };
+        if fd < 0 {
+            Err(Error::from(ErrorKind::Generic))
+        } else {
+            Ok(fd)
+        }
+    }
+
     /// Wait for `timeout` seconds for an `Event`. Passing `0` as `timeout` will poll.
     /// For more information, as always, see the mpv-sys docs of `mpv_wait_event`.
     ///
@@ -218,11 +765,29 @@ impl<'parent> EventContext<'parent> {
     ///
     /// Returns `Some(Err(...))` if there was invalid utf-8, or if either an
     /// `MPV_EVENT_GET_PROPERTY_REPLY`, `MPV_EVENT_SET_PROPERTY_REPLY`, `MPV_EVENT_COMMAND_REPLY`,
-    /// or `MPV_EVENT_PROPERTY_CHANGE` event failed, or if `MPV_EVENT_END_FILE` reported an error.
+    /// or `MPV_EVENT_PROPERTY_CHANGE` event failed. Errors reported by `MPV_EVENT_END_FILE` are
+    /// part of `Event::EndFile` instead.
     pub fn wait_event(&mut self, timeout: f64) -> Option<Result<Event>> {
+        self.wait_event_raw(timeout).map(|(_, _, event)| event)
+    }
+
+    /// Like `wait_event`, but also returns the `EventId` and `reply_userdata` of the event,
+    /// which are needed to tell which request failed.
+    pub(crate) fn wait_event_raw(&mut self, timeout: f64) -> Option<(EventId, u64, Result<Event>)> {
         let event = unsafe { *libmpv_sys::mpv_wait_event(self.ctx.as_ptr(), timeout) };
+        self.decode_event(event)
+            .map(|result| (event.event_id, event.reply_userdata, result))
+    }
+
+    fn decode_event(&mut self, event: mpv_event) -> Option<Result<Event>> {
         if event.event_id != mpv_event_id::None {
             if let Err(e) = mpv_err((), event.error) {
+                if event.event_id == mpv_event_id::GetPropertyReply {
+                    let property = unsafe { *(event.data as *mut libmpv_sys::mpv_event_property) };
+                    if let Ok(name) = unsafe { mpv_cstr_to_str!(property.name) } {
+                        return Some(Err(e.in_property(name)));
+                    }
+                }
                 return Some(Err(e));
             }
         }
@@ -267,17 +832,23 @@ impl<'parent> EventContext<'parent> {
                 Event::CommandReply(event.reply_userdata),
                 event.error,
             )),
-            mpv_event_id::StartFile => Some(Ok(Event::StartFile)),
+            mpv_event_id::StartFile => {
+                let start_file = unsafe { *(event.data as *mut libmpv_sys::mpv_event_start_file) };
+
+                Some(Ok(Event::StartFile {
+                    playlist_entry_id: start_file.playlist_entry_id,
+                }))
+            }
             mpv_event_id::EndFile => {
                 let end_file = unsafe { *(event.data as *mut libmpv_sys::mpv_event_end_file) };
 
-                if let Err(e) = mpv_err((), end_file.error) {
-                    Some(Err(e))
-                } else if end_file.reason.is_positive() {
-                    Some(Ok(Event::EndFile(end_file.reason as _)))
-                } else {
-                    None
-                }
+                Some(Ok(Event::EndFile {
+                    reason: end_file.reason as _,
+                    error: mpv_err((), end_file.error).err(),
+                    playlist_entry_id: end_file.playlist_entry_id,
+                    playlist_insert_id: end_file.playlist_insert_id,
+                    playlist_insert_num_entries: end_file.playlist_insert_num_entries,
+                }))
             }
             mpv_event_id::FileLoaded => Some(Ok(Event::FileLoaded)),
             mpv_event_id::ClientMessage => {
@@ -286,13 +857,13 @@ impl<'parent> EventContext<'parent> {
                 let messages = unsafe {
                     slice::from_raw_parts_mut(client_message.args, client_message.num_args as _)
                 };
-                Some(Ok(Event::ClientMessage(
+                Some(
                     messages
                         .iter()
                         .map(|msg| unsafe { mpv_cstr_to_str!(*msg) })
                         .collect::<Result<Vec<_>>>()
-                        .unwrap(),
-                )))
+                        .map(Event::ClientMessage),
+                )
             }
             mpv_event_id::VideoReconfig => Some(Ok(Event::VideoReconfig)),
             mpv_event_id::AudioReconfig => Some(Ok(Event::AudioReconfig)),
@@ -301,26 +872,45 @@ impl<'parent> EventContext<'parent> {
             mpv_event_id::PropertyChange => {
                 let property = unsafe { *(event.data as *mut libmpv_sys::mpv_event_property) };
 
-                // This happens if the property is not available. For example,
-                // if you reached EndFile while observing a property.
-                if property.format == mpv_format::None {
-                    None
-                } else {
-                    let name = unsafe { mpv_cstr_to_str!(property.name) };
-                    Some(name.and_then(|name| {
-                        // SAFETY: safe because we are passing format + data from an mpv_event_property
-                        let change =
-                            unsafe { PropertyData::from_raw(property.format, property.data) }?;
-
-                        Ok(Event::PropertyChange {
-                            name,
-                            change,
-                            reply_userdata: event.reply_userdata,
-                        })
-                    }))
-                }
+                let name = unsafe { mpv_cstr_to_str!(property.name) };
+                Some(name.and_then(|name| {
+                    // SAFETY: safe because we are passing format + data from an mpv_event_property
+                    let change = unsafe { PropertyData::from_raw(property.format, property.data) }?;
+
+                    let callback = self
+                        .observers
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .get(&event.reply_userdata)
+                        .cloned();
+                    if let Some(callback) = callback {
+                        let mut callback = callback.lock().unwrap_or_else(PoisonError::into_inner);
+                        (*callback)(&change);
+                    }
+
+                    Ok(Event::PropertyChange {
+                        name,
+                        change,
+                        reply_userdata: event.reply_userdata,
+                    })
+                }))
             }
             mpv_event_id::QueueOverflow => Some(Ok(Event::QueueOverflow)),
+            mpv_event_id::Hook => {
+                let hook = unsafe { *(event.data as *mut libmpv_sys::mpv_event_hook) };
+                let id = HookId {
+                    ctx: self.ctx,
+                    id: hook.id,
+                    _does_not_outlive: PhantomData,
+                };
+
+                let name = unsafe { mpv_cstr_to_str!(hook.name) };
+                Some(name.map(|name| Event::Hook {
+                    name,
+                    reply_userdata: event.reply_userdata,
+                    id,
+                }))
+            }
             _ => Some(Ok(Event::Deprecated(event))),
         }
     }
@@ -0,0 +1,197 @@
+use super::*;
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Where `Mpv::load_file` puts a file into the playlist.
+pub enum LoadMode {
+    /// Stop playback, and replace the playlist with the file.
+    Replace,
+    /// Append the file to the playlist.
+    Append,
+    /// Append the file, and play it if nothing else is playing.
+    AppendPlay,
+    /// Insert the file at this playlist index, or append it if the index is out of range.
+    /// Requires mpv 0.38 or newer.
+    InsertAt(i64),
+    /// Insert the file after the current entry. Requires mpv 0.38 or newer.
+    InsertNext,
+}
+
+impl LoadMode {
+    fn val(&self) -> &str {
+        match *self {
+            LoadMode::Replace => "replace",
+            LoadMode::Append => "append",
+            LoadMode::AppendPlay => "append-play",
+            LoadMode::InsertAt(_) => "insert-at",
+            LoadMode::InsertNext => "insert-next",
+        }
+    }
+}
+
+/// A file to load with `Mpv::load_file`, with options that only apply while it is played.
+///
+/// Options are passed to mpv as a map, so values need no quoting or escaping.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadFile {
+    url: String,
+    mode: LoadMode,
+    options: BTreeMap<String, String>,
+}
+
+impl LoadFile {
+    /// Load `url`, which is a path or any URL mpv supports, replacing the playlist.
+    pub fn new(url: &str) -> LoadFile {
+        LoadFile {
+            url: url.to_owned(),
+            mode: LoadMode::Replace,
+            options: BTreeMap::new(),
+        }
+    }
+
+    /// Where the file is put into the playlist, see `LoadMode`.
+    pub fn mode(mut self, mode: LoadMode) -> LoadFile {
+        self.mode = mode;
+        self
+    }
+
+    /// Stop playback, and replace the playlist with the file. This is the default.
+    pub fn replace(self) -> LoadFile {
+        self.mode(LoadMode::Replace)
+    }
+
+    /// Append the file to the playlist.
+    pub fn append(self) -> LoadFile {
+        self.mode(LoadMode::Append)
+    }
+
+    /// Append the file, and play it if nothing else is playing.
+    pub fn append_play(self) -> LoadFile {
+        self.mode(LoadMode::AppendPlay)
+    }
+
+    /// Insert the file at the playlist `index`. Requires mpv 0.38 or newer.
+    pub fn insert_at(self, index: i64) -> LoadFile {
+        self.mode(LoadMode::InsertAt(index))
+    }
+
+    /// Insert the file after the current entry. Requires mpv 0.38 or newer.
+    pub fn insert_next(self) -> LoadFile {
+        self.mode(LoadMode::InsertNext)
+    }
+
+    /// Set any option for this file from its string representation, like `--name=value`.
+    pub fn option(mut self, name: &str, value: &str) -> LoadFile {
+        self.options.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Start playback at this position in seconds, which counts from the end if negative.
+    pub fn start(self, secs: f64) -> LoadFile {
+        self.option("start", &secs.to_string())
+    }
+
+    /// Stop playback at this position in seconds.
+    pub fn end(self, secs: f64) -> LoadFile {
+        self.option("end", &secs.to_string())
+    }
+
+    /// Loop between the positions `a` and `b` in seconds.
+    pub fn ab_loop(self, a: f64, b: f64) -> LoadFile {
+        self.option("ab-loop-a", &a.to_string())
+            .option("ab-loop-b", &b.to_string())
+    }
+
+    /// The audio track that is played, see `Mpv::audio_select`.
+    pub fn audio(self, track: TrackSelection) -> LoadFile {
+        self.option("aid", &track.val())
+    }
+
+    /// The subtitle track that is shown, see `Mpv::subtitle_select`.
+    pub fn subtitle(self, track: TrackSelection) -> LoadFile {
+        self.option("sid", &track.val())
+    }
+
+    fn to_command(&self) -> Node {
+        let mut args = BTreeMap::new();
+        args.insert("name".to_owned(), Node::from("loadfile"));
+        args.insert("url".to_owned(), Node::from(self.url.as_str()));
+        args.insert("flags".to_owned(), Node::from(self.mode.val()));
+        if let LoadMode::InsertAt(index) = self.mode {
+            args.insert("index".to_owned(), Node::from(index));
+        }
+        if !self.options.is_empty() {
+            let options = self
+                .options
+                .iter()
+                .map(|(name, value)| (name.clone(), Node::from(value.as_str())))
+                .collect::<BTreeMap<_, _>>();
+            args.insert("options".to_owned(), Node::from(options));
+        }
+        Node::from(args)
+    }
+}
+
+impl Mpv {
+    /// Load `file` into the playlist, and return the id of its new playlist entry, which is
+    /// used by `PlaylistEntry::id` and the `StartFile` and `EndFile` events. The id is `None`
+    /// if mpv does not report it, which older versions do not, even though the file was added.
+    ///
+    /// Like `loadfile`, this returns before the file is opened, so errors while loading are
+    /// only reported by `Event::EndFile`.
+    pub fn load_file(&self, file: &LoadFile) -> Result<Option<i64>> {
+        let result = self.command_node(&file.to_command())?.to_node()?;
+        Ok(result.get("playlist_entry_id").and_then(Node::as_i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_command() {
+        let command = LoadFile::new("a.mkv").to_command();
+        assert_eq!(command.get("name"), Some(&Node::from("loadfile")));
+        assert_eq!(command.get("url"), Some(&Node::from("a.mkv")));
+        assert_eq!(command.get("flags"), Some(&Node::from("replace")));
+        assert_eq!(command.get("index"), None);
+        assert_eq!(command.get("options"), None);
+
+        let flags = |file: LoadFile| file.to_command().get("flags").cloned();
+        assert_eq!(
+            flags(LoadFile::new("a").append()),
+            Some(Node::from("append"))
+        );
+        assert_eq!(
+            flags(LoadFile::new("a").append_play()),
+            Some(Node::from("append-play"))
+        );
+        assert_eq!(
+            flags(LoadFile::new("a").insert_next()),
+            Some(Node::from("insert-next"))
+        );
+        assert_eq!(
+            flags(LoadFile::new("a").append().replace()),
+            Some(Node::from("replace"))
+        );
+
+        let command = LoadFile::new("a").insert_at(2).to_command();
+        assert_eq!(command.get("flags"), Some(&Node::from("insert-at")));
+        assert_eq!(command.get("index"), Some(&Node::from(2i64)));
+    }
+
+    #[test]
+    fn to_command_options() {
+        let command = LoadFile::new("a")
+            .start(1.5)
+            .ab_loop(2.0, 3.25)
+            .to_command();
+        let mut options = BTreeMap::new();
+        options.insert("start".to_owned(), Node::from("1.5"));
+        options.insert("ab-loop-a".to_owned(), Node::from("2"));
+        options.insert("ab-loop-b".to_owned(), Node::from("3.25"));
+        assert_eq!(command.get("options"), Some(&Node::from(options)));
+    }
+}
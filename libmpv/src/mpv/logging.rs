@@ -0,0 +1,76 @@
+//! Routing of mpv's log messages, as received with `Event::LogMessage` after calling
+//! `Mpv::request_log_messages`.
+//!
+//! With the `log` or `tracing` features, messages can be forwarded to the respective crate,
+//! e.g. from an event loop:
+//!
+//! ```ignore
+//! if let Event::LogMessage { prefix, log_level, text, .. } = event {
+//!     libmpv::logging::to_log(prefix, log_level, text);
+//! }
+//! ```
+
+use crate::mpv::mpv_err;
+use crate::*;
+
+use std::ffi::CString;
+
+fn level_name(level: LogLevel) -> Option<&'static str> {
+    Some(match level {
+        mpv_log_level::None => "no",
+        mpv_log_level::Fatal => "fatal",
+        mpv_log_level::Error => "error",
+        mpv_log_level::Warn => "warn",
+        mpv_log_level::Info => "info",
+        mpv_log_level::V => "v",
+        mpv_log_level::Debug => "debug",
+        mpv_log_level::Trace => "trace",
+        _ => return None,
+    })
+}
+
+impl Mpv {
+    /// Receive log messages of at least `level` as `Event::LogMessage`, or none with
+    /// `mpv_log_level::None`. Log messages are not received by default.
+    pub fn request_log_messages(&self, level: LogLevel) -> Result<()> {
+        let level = level_name(level).ok_or(Error::from(ErrorKind::InvalidParameter))?;
+        let level = CString::new(level)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_request_log_messages(self.ctx.as_ptr(), level.as_ptr())
+        })
+    }
+}
+
+/// Forward a log message to the `log` crate, with `prefix` (the mpv module, e.g. `ffmpeg` or
+/// `demux`) as target.
+#[cfg(feature = "log")]
+pub fn to_log(prefix: &str, level: LogLevel, text: &str) {
+    let level = match level {
+        mpv_log_level::Fatal | mpv_log_level::Error => log::Level::Error,
+        mpv_log_level::Warn => log::Level::Warn,
+        mpv_log_level::Info => log::Level::Info,
+        mpv_log_level::V => log::Level::Debug,
+        mpv_log_level::Debug | mpv_log_level::Trace => log::Level::Trace,
+        _ => return,
+    };
+    log::log!(target: prefix, level, "{}", text.trim_end());
+}
+
+/// Forward a log message to the `tracing` crate. Since targets have to be static, events use
+/// `mpv` as target, and carry `prefix` (the mpv module, e.g. `ffmpeg` or `demux`) as field.
+#[cfg(feature = "tracing")]
+pub fn to_tracing(prefix: &str, level: LogLevel, text: &str) {
+    let text = text.trim_end();
+    match level {
+        mpv_log_level::Fatal | mpv_log_level::Error => {
+            tracing::error!(target: "mpv", prefix, "{}", text)
+        }
+        mpv_log_level::Warn => tracing::warn!(target: "mpv", prefix, "{}", text),
+        mpv_log_level::Info => tracing::info!(target: "mpv", prefix, "{}", text),
+        mpv_log_level::V => tracing::debug!(target: "mpv", prefix, "{}", text),
+        mpv_log_level::Debug | mpv_log_level::Trace => {
+            tracing::trace!(target: "mpv", prefix, "{}", text)
+        }
+        _ => (),
+    }
+}
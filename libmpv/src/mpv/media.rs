@@ -0,0 +1,194 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// The kind of a `Track`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrackType {
+    Audio,
+    Video,
+    Sub,
+}
+
+impl TrackType {
+    /// The name mpv uses for this track type, e.g. in the `type` field of `track-list`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TrackType::Audio => "audio",
+            TrackType::Video => "video",
+            TrackType::Sub => "sub",
+        }
+    }
+}
+
+/// An audio, video or subtitle track of the current file, as listed in `track-list`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    /// The track id, as used with the `aid`, `vid` and `sid` properties.
+    pub id: i64,
+    pub track_type: TrackType,
+    /// The track id as stored in the file.
+    pub src_id: Option<i64>,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    pub selected: bool,
+    /// Whether the track was loaded from an external file, e.g. with `sub-add`.
+    pub external: bool,
+    pub external_filename: Option<String>,
+    /// Video width in pixels, as reported by the demuxer.
+    pub demux_w: Option<i64>,
+    /// Video height in pixels, as reported by the demuxer.
+    pub demux_h: Option<i64>,
+    pub demux_fps: Option<f64>,
+    pub demux_channel_count: Option<i64>,
+    /// Channel layout, e.g. `stereo` or `5.1(side)`.
+    pub demux_channels: Option<String>,
+    pub demux_samplerate: Option<i64>,
+}
+
+/// A chapter of the current file, as listed in `chapter-list`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub title: Option<String>,
+    /// Start time of the chapter in seconds.
+    pub time: f64,
+}
+
+/// An entry of the playlist, as listed in `playlist`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    pub filename: String,
+    pub title: Option<String>,
+    /// The playlist entry id, only set by mpv 0.33 and newer.
+    pub id: Option<i64>,
+    /// Whether this is the current entry.
+    pub current: bool,
+    /// Whether this entry is being played.
+    pub playing: bool,
+}
+
+fn field<'a>(node: &'a MpvNode, key: &str) -> Option<&'a MpvNode> {
+    node.to_map()?.find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+fn str_field(node: &MpvNode, key: &str) -> Option<String> {
+    field(node, key)?.to_str().map(str::to_owned)
+}
+
+fn i64_field(node: &MpvNode, key: &str) -> Option<i64> {
+    field(node, key)?.to_i64()
+}
+
+fn f64_field(node: &MpvNode, key: &str) -> Option<f64> {
+    field(node, key)?.to_f64()
+}
+
+fn bool_field(node: &MpvNode, key: &str) -> bool {
+    field(node, key).and_then(MpvNode::to_bool).unwrap_or(false)
+}
+
+fn missing(list: &str, key: &str) -> Error {
+    Error::Deserialize(format!("{} entry without `{}`", list, key))
+}
+
+fn decode_list<T, F: Fn(&MpvNode) -> Result<T>>(
+    node: &MpvNode,
+    list: &str,
+    decode: F,
+) -> Result<Vec<T>> {
+    node.to_array()
+        .ok_or_else(|| Error::Deserialize(format!("{} is not an array", list)))?
+        .map(decode)
+        .collect()
+}
+
+impl Track {
+    fn from_node(node: &MpvNode) -> Result<Track> {
+        let track_type = match field(node, "type").and_then(MpvNode::to_str) {
+            Some("audio") => TrackType::Audio,
+            Some("video") => TrackType::Video,
+            Some("sub") => TrackType::Sub,
+            Some(other) => {
+                return Err(Error::Deserialize(format!(
+                    "unknown track type `{}`",
+                    other
+                )))
+            }
+            None => return Err(missing("track-list", "type")),
+        };
+
+        Ok(Track {
+            id: i64_field(node, "id").ok_or_else(|| missing("track-list", "id"))?,
+            track_type,
+            src_id: i64_field(node, "src-id"),
+            title: str_field(node, "title"),
+            lang: str_field(node, "lang"),
+            codec: str_field(node, "codec"),
+            default: bool_field(node, "default"),
+            forced: bool_field(node, "forced"),
+            selected: bool_field(node, "selected"),
+            external: bool_field(node, "external"),
+            external_filename: str_field(node, "external-filename"),
+            demux_w: i64_field(node, "demux-w"),
+            demux_h: i64_field(node, "demux-h"),
+            demux_fps: f64_field(node, "demux-fps"),
+            demux_channel_count: i64_field(node, "demux-channel-count"),
+            demux_channels: str_field(node, "demux-channels"),
+            demux_samplerate: i64_field(node, "demux-samplerate"),
+        })
+    }
+}
+
+impl Chapter {
+    fn from_node(node: &MpvNode) -> Result<Chapter> {
+        Ok(Chapter {
+            title: str_field(node, "title"),
+            time: f64_field(node, "time").ok_or_else(|| missing("chapter-list", "time"))?,
+        })
+    }
+}
+
+impl PlaylistEntry {
+    fn from_node(node: &MpvNode) -> Result<PlaylistEntry> {
+        Ok(PlaylistEntry {
+            filename: str_field(node, "filename").ok_or_else(|| missing("playlist", "filename"))?,
+            title: str_field(node, "title"),
+            id: i64_field(node, "id"),
+            current: bool_field(node, "current"),
+            playing: bool_field(node, "playing"),
+        })
+    }
+}
+
+impl Mpv {
+    /// All audio, video and subtitle tracks of the current file.
+    pub fn tracks(&self) -> Result<Vec<Track>> {
+        let node = self.get_property::<MpvNode>("track-list")?;
+        decode_list(&node, "track-list", Track::from_node)
+    }
+
+    /// All chapters of the current file.
+    pub fn chapters(&self) -> Result<Vec<Chapter>> {
+        let node = self.get_property::<MpvNode>("chapter-list")?;
+        decode_list(&node, "chapter-list", Chapter::from_node)
+    }
+
+    /// Metadata tags of the current file. Entries which are not strings are skipped.
+    pub fn metadata(&self) -> Result<HashMap<String, String>> {
+        let node = self.get_property::<MpvNode>("metadata")?;
+        Ok(node
+            .to_map()
+            .ok_or_else(|| Error::Deserialize("metadata is not a map".to_owned()))?
+            .filter_map(|(key, value)| Some((key.to_owned(), value.to_str()?.to_owned())))
+            .collect())
+    }
+
+    /// All entries of the playlist.
+    pub fn playlist(&self) -> Result<Vec<PlaylistEntry>> {
+        let node = self.get_property::<MpvNode>("playlist")?;
+        decode_list(&node, "playlist", PlaylistEntry::from_node)
+    }
+}
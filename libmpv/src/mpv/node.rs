@@ -0,0 +1,534 @@
+use super::*;
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::os::raw as ctype;
+
+/// An owned mpv node, that can be constructed in Rust, and passed to `Mpv::set_property`,
+/// `Mpv::command_node` and friends.
+///
+/// With the `serde` feature, nodes implement `Serialize` and `Deserialize`, and can be decoded
+/// into any `Deserialize` type using `from_node`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    None,
+    String(String),
+    Flag(bool),
+    Int64(i64),
+    Double(f64),
+    Array(Vec<Node>),
+    Map(BTreeMap<String, Node>),
+    ByteArray(Vec<u8>),
+}
+
+impl Node {
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Node::Flag(value) = *self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// The value as an integer, which may also be a double without a fractional part.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Node::Int64(value) => Some(value),
+            Node::Double(value) => f64_to_i64(value),
+            _ => None,
+        }
+    }
+
+    /// The value as a double, which may also be an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Node::Double(value) => Some(value),
+            Node::Int64(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let Node::String(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Node]> {
+        if let Node::Array(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Node>> {
+        if let Node::Map(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        if let Node::ByteArray(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Look up `key` if this node is a map.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.as_map()?.get(key)
+    }
+}
+
+impl From<&str> for Node {
+    fn from(value: &str) -> Node {
+        Node::String(value.to_owned())
+    }
+}
+
+impl From<String> for Node {
+    fn from(value: String) -> Node {
+        Node::String(value)
+    }
+}
+
+impl From<bool> for Node {
+    fn from(value: bool) -> Node {
+        Node::Flag(value)
+    }
+}
+
+impl From<i64> for Node {
+    fn from(value: i64) -> Node {
+        Node::Int64(value)
+    }
+}
+
+impl From<f64> for Node {
+    fn from(value: f64) -> Node {
+        Node::Double(value)
+    }
+}
+
+impl From<Vec<Node>> for Node {
+    fn from(value: Vec<Node>) -> Node {
+        Node::Array(value)
+    }
+}
+
+impl From<BTreeMap<String, Node>> for Node {
+    fn from(value: BTreeMap<String, Node>) -> Node {
+        Node::Map(value)
+    }
+}
+
+impl MpvNode {
+    /// Copy this node, and all of its children, into an owned `Node`.
+    pub fn to_node(&self) -> Result<Node> {
+        Ok(match self.value()? {
+            MpvNodeValue::None => Node::None,
+            MpvNodeValue::String(value) => Node::String(value.to_owned()),
+            MpvNodeValue::Flag(value) => Node::Flag(value),
+            MpvNodeValue::Int64(value) => Node::Int64(value),
+            MpvNodeValue::Double(value) => Node::Double(value),
+            MpvNodeValue::ByteArray(value) => Node::ByteArray(value.to_vec()),
+            MpvNodeValue::Array(iter) => {
+                Node::Array(iter.map(MpvNode::to_node).collect::<Result<_>>()?)
+            }
+            MpvNodeValue::Map(iter) => Node::Map(
+                iter.map(|(key, value)| Ok((key.to_owned(), value.to_node()?)))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+unsafe impl GetData for Node {
+    fn get_from_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(fun: F) -> Result<Node> {
+        MpvNode::get_from_c_void(fun)?.to_node()
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+/// Bytes are requested as a node, since mpv only returns byte arrays inside nodes.
+unsafe impl GetData for Vec<u8> {
+    fn get_from_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(fun: F) -> Result<Vec<u8>> {
+        MpvNode::get_from_c_void(fun)?
+            .to_bytes()
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| Error::from(ErrorKind::PropertyFormat))
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+unsafe impl SetData for Node {
+    fn call_as_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(self, fun: F) -> Result<T> {
+        (&self).call_as_c_void(fun)
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+unsafe impl SetData for &Node {
+    fn call_as_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(self, mut fun: F) -> Result<T> {
+        let mut raw = RawNode::new(self)?;
+        fun(&mut raw.node as *mut libmpv_sys::mpv_node as *mut _)
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+/// An `mpv_node` pointing into Rust-owned memory, which lives as long as this struct.
+pub(crate) struct RawNode {
+    pub(crate) node: libmpv_sys::mpv_node,
+    _storage: RawNodeStorage,
+}
+
+enum RawNodeStorage {
+    None,
+    String {
+        _string: CString,
+    },
+    List {
+        _list: Box<libmpv_sys::mpv_node_list>,
+        _children: Vec<RawNode>,
+        _values: Vec<libmpv_sys::mpv_node>,
+        _keys: Vec<CString>,
+        _key_ptrs: Vec<*mut ctype::c_char>,
+    },
+    ByteArray {
+        _ba: Box<libmpv_sys::mpv_byte_array>,
+        _data: Vec<u8>,
+    },
+}
+
+impl RawNode {
+    pub(crate) fn new(node: &Node) -> Result<RawNode> {
+        let mut raw = libmpv_sys::mpv_node {
+            u: libmpv_sys::mpv_node__bindgen_ty_1 { int64: 0 },
+            format: mpv_format::None,
+        };
+
+        let storage = match node {
+            Node::None => RawNodeStorage::None,
+            Node::String(value) => {
+                let string = CString::new(&value[..])?;
+                raw.format = mpv_format::String;
+                raw.u.string = string.as_ptr() as *mut _;
+                RawNodeStorage::String { _string: string }
+            }
+            Node::Flag(value) => {
+                raw.format = mpv_format::Flag;
+                raw.u.flag = *value as ctype::c_int;
+                RawNodeStorage::None
+            }
+            Node::Int64(value) => {
+                raw.format = mpv_format::Int64;
+                raw.u.int64 = *value;
+                RawNodeStorage::None
+            }
+            Node::Double(value) => {
+                raw.format = mpv_format::Double;
+                raw.u.double_ = *value;
+                RawNodeStorage::None
+            }
+            Node::Array(values) => {
+                let children = values.iter().map(RawNode::new).collect::<Result<_>>()?;
+                raw.format = mpv_format::Array;
+                RawNode::list_storage(&mut raw, children, Vec::new())?
+            }
+            Node::Map(values) => {
+                let children = values.values().map(RawNode::new).collect::<Result<_>>()?;
+                let keys = values
+                    .keys()
+                    .map(|key| CString::new(&key[..]))
+                    .collect::<::std::result::Result<_, _>>()?;
+                raw.format = mpv_format::Map;
+                RawNode::list_storage(&mut raw, children, keys)?
+            }
+            Node::ByteArray(data) => {
+                let mut data = data.clone();
+                let mut ba = Box::new(libmpv_sys::mpv_byte_array {
+                    data: data.as_mut_ptr() as *mut _,
+                    size: data.len(),
+                });
+                raw.format = mpv_format::ByteArray;
+                raw.u.ba = &mut *ba;
+                RawNodeStorage::ByteArray {
+                    _ba: ba,
+                    _data: data,
+                }
+            }
+        };
+
+        Ok(RawNode {
+            node: raw,
+            _storage: storage,
+        })
+    }
+
+    fn list_storage(
+        raw: &mut libmpv_sys::mpv_node,
+        children: Vec<RawNode>,
+        keys: Vec<CString>,
+    ) -> Result<RawNodeStorage> {
+        let mut values: Vec<_> = children.iter().map(|child| child.node).collect();
+        let mut key_ptrs: Vec<_> = keys.iter().map(|key| key.as_ptr() as *mut _).collect();
+        let mut list = Box::new(libmpv_sys::mpv_node_list {
+            num: values
+                .len()
+                .try_into()
+                .map_err(|_| Error::from(ErrorKind::InvalidParameter))?,
+            values: values.as_mut_ptr(),
+            keys: if key_ptrs.is_empty() {
+                ptr::null_mut()
+            } else {
+                key_ptrs.as_mut_ptr()
+            },
+        });
+        raw.u.list = &mut *list;
+
+        Ok(RawNodeStorage::List {
+            _list: list,
+            _children: children,
+            _values: values,
+            _keys: keys,
+            _key_ptrs: key_ptrs,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+
+    use serde::de::{
+        self,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    };
+    use serde::ser::{SerializeMap, SerializeSeq};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt;
+
+    /// Decode a `Node`, e.g. the value of `track-list`, into any `Deserialize` type.
+    pub fn from_node<T: DeserializeOwned>(node: Node) -> Result<T> {
+        T::deserialize(node)
+    }
+
+    impl de::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Error {
+            Error::Deserialize(msg.to_string())
+        }
+    }
+
+    impl Serialize for Node {
+        fn serialize<S: Serializer>(
+            &self,
+            serializer: S,
+        ) -> ::std::result::Result<S::Ok, S::Error> {
+            match self {
+                Node::None => serializer.serialize_none(),
+                Node::String(value) => serializer.serialize_str(value),
+                Node::Flag(value) => serializer.serialize_bool(*value),
+                Node::Int64(value) => serializer.serialize_i64(*value),
+                Node::Double(value) => serializer.serialize_f64(*value),
+                Node::Array(values) => {
+                    let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                    for value in values {
+                        seq.serialize_element(value)?;
+                    }
+                    seq.end()
+                }
+                Node::Map(values) => {
+                    let mut map = serializer.serialize_map(Some(values.len()))?;
+                    for (key, value) in values {
+                        map.serialize_entry(key, value)?;
+                    }
+                    map.end()
+                }
+                Node::ByteArray(value) => serializer.serialize_bytes(value),
+            }
+        }
+    }
+
+    struct NodeVisitor;
+
+    impl<'de> Visitor<'de> for NodeVisitor {
+        type Value = Node;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a value representable as an mpv node")
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> ::std::result::Result<Node, E> {
+            Ok(Node::Flag(value))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> ::std::result::Result<Node, E> {
+            Ok(Node::Int64(value))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> ::std::result::Result<Node, E> {
+            value
+                .try_into()
+                .map(Node::Int64)
+                .or(Ok(Node::Double(value as f64)))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> ::std::result::Result<Node, E> {
+            Ok(Node::Double(value))
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> ::std::result::Result<Node, E> {
+            Ok(Node::String(value.to_owned()))
+        }
+
+        fn visit_string<E: de::Error>(self, value: String) -> ::std::result::Result<Node, E> {
+            Ok(Node::String(value))
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> ::std::result::Result<Node, E> {
+            Ok(Node::ByteArray(value.to_owned()))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> ::std::result::Result<Node, E> {
+            Ok(Node::ByteArray(value))
+        }
+
+        fn visit_none<E: de::Error>(self) -> ::std::result::Result<Node, E> {
+            Ok(Node::None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> ::std::result::Result<Node, D::Error> {
+            Node::deserialize(deserializer)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> ::std::result::Result<Node, E> {
+            Ok(Node::None)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> ::std::result::Result<Node, A::Error> {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(Node::Array(values))
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> ::std::result::Result<Node, A::Error> {
+            let mut values = BTreeMap::new();
+            while let Some((key, value)) = map.next_entry()? {
+                values.insert(key, value);
+            }
+            Ok(Node::Map(values))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Node {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> ::std::result::Result<Node, D::Error> {
+            deserializer.deserialize_any(NodeVisitor)
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Node {
+        type Deserializer = Node;
+
+        fn into_deserializer(self) -> Node {
+            self
+        }
+    }
+
+    impl<'de> Deserializer<'de> for Node {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self {
+                Node::None => visitor.visit_unit(),
+                Node::String(value) => visitor.visit_string(value),
+                Node::Flag(value) => visitor.visit_bool(value),
+                Node::Int64(value) => visitor.visit_i64(value),
+                Node::Double(value) => visitor.visit_f64(value),
+                Node::Array(values) => {
+                    let mut seq = SeqDeserializer::new(values.into_iter());
+                    let ret = visitor.visit_seq(&mut seq)?;
+                    seq.end()?;
+                    Ok(ret)
+                }
+                Node::Map(values) => {
+                    let mut map = MapDeserializer::new(values.into_iter());
+                    let ret = visitor.visit_map(&mut map)?;
+                    map.end()?;
+                    Ok(ret)
+                }
+                Node::ByteArray(value) => visitor.visit_byte_buf(value),
+            }
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self {
+                Node::None => visitor.visit_none(),
+                node => visitor.visit_some(node),
+            }
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value> {
+            match self {
+                Node::String(value) => visitor.visit_enum(value.into_deserializer()),
+                Node::Map(values) if values.len() == 1 => visitor.visit_enum(
+                    MapAccessDeserializer::new(MapDeserializer::new(values.into_iter())),
+                ),
+                node => node.deserialize_any(visitor),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+            bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+            identifier ignored_any
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use self::serde_impls::from_node;
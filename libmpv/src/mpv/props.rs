@@ -0,0 +1,273 @@
+//! A catalog of typed mpv properties, usable with `Mpv::get`, `Mpv::set` and
+//! `EventContext::observe`, so that property names and types are checked at compile time.
+//!
+//! See the [list of properties](https://mpv.io/manual/master/#property-list) in the mpv manual.
+
+use crate::events::EventContext;
+use crate::*;
+
+/// A property with a known name and type.
+pub trait Property {
+    /// The name of the property, as used by mpv.
+    const NAME: &'static str;
+    /// The type the property is read as. Its format is given by `GetData::get_format`.
+    type Value: GetData;
+}
+
+/// A property that can be set at runtime.
+pub trait WritableProperty: Property {}
+
+macro_rules! property {
+    ($(#[$attr:meta])* $ident:ident, $name:literal, $value:ty) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $ident;
+
+        impl Property for $ident {
+            const NAME: &'static str = $name;
+            type Value = $value;
+        }
+    };
+    ($(#[$attr:meta])* $ident:ident, $name:literal, $value:ty, writable) => {
+        property!($(#[$attr])* $ident, $name, $value);
+
+        impl WritableProperty for $ident {}
+    };
+}
+
+// --- Playback ---
+//
+
+property!(
+    /// Position in the current file in seconds.
+    TimePos,
+    "time-pos",
+    f64,
+    writable
+);
+property!(
+    /// Position in the current file in seconds, clamped to the file's duration.
+    PlaybackTime,
+    "playback-time",
+    f64,
+    writable
+);
+property!(
+    /// Remaining length of the current file in seconds.
+    TimeRemaining,
+    "time-remaining",
+    f64
+);
+property!(
+    /// Duration of the current file in seconds.
+    Duration,
+    "duration",
+    f64
+);
+property!(
+    /// Position in the current file in percent.
+    PercentPos,
+    "percent-pos",
+    f64,
+    writable
+);
+property!(
+    /// Whether playback is paused.
+    Pause,
+    "pause",
+    bool,
+    writable
+);
+property!(
+    /// Playback speed factor.
+    Speed,
+    "speed",
+    f64,
+    writable
+);
+property!(
+    /// Whether the player is currently seeking.
+    Seeking,
+    "seeking",
+    bool
+);
+property!(
+    /// Whether the end of the current file was reached, only with `keep-open`.
+    EofReached,
+    "eof-reached",
+    bool
+);
+property!(
+    /// Whether no file is loaded, and the player is idling.
+    IdleActive,
+    "idle-active",
+    bool
+);
+property!(
+    /// Whether and how often the current file loops, either `inf`, `no` or a count.
+    LoopFile,
+    "loop-file",
+    String,
+    writable
+);
+
+// --- Audio ---
+//
+
+property!(
+    /// Volume in percent.
+    Volume,
+    "volume",
+    f64,
+    writable
+);
+property!(
+    /// Whether audio is muted.
+    Mute,
+    "mute",
+    bool,
+    writable
+);
+property!(
+    /// Audio delay in seconds.
+    AudioDelay,
+    "audio-delay",
+    f64,
+    writable
+);
+
+// --- Video and subtitles ---
+//
+
+property!(
+    /// Width of the video in pixels, before filters are applied.
+    Width,
+    "width",
+    i64
+);
+property!(
+    /// Height of the video in pixels, before filters are applied.
+    Height,
+    "height",
+    i64
+);
+property!(
+    /// Hardware decoding API, e.g. `auto` or `no`.
+    Hwdec,
+    "hwdec",
+    String,
+    writable
+);
+property!(
+    /// Subtitle delay in seconds.
+    SubDelay,
+    "sub-delay",
+    f64,
+    writable
+);
+property!(
+    /// Whether subtitles are shown.
+    SubVisibility,
+    "sub-visibility",
+    bool,
+    writable
+);
+
+// --- File information ---
+//
+
+property!(
+    /// Path or URL of the current file.
+    Path,
+    "path",
+    String
+);
+property!(
+    /// File name of the current file, without its directory.
+    Filename,
+    "filename",
+    String
+);
+property!(
+    /// Title of the current file, taken from its metadata, or its file name.
+    MediaTitle,
+    "media-title",
+    String
+);
+property!(
+    /// Metadata of the current file as a map.
+    Metadata,
+    "metadata",
+    Node
+);
+
+// --- Tracks, chapters and playlist ---
+//
+
+property!(
+    /// All audio, video and subtitle tracks of the current file.
+    TrackList,
+    "track-list",
+    Node
+);
+property!(
+    /// Current chapter, starting at `0`.
+    Chapter,
+    "chapter",
+    i64,
+    writable
+);
+property!(
+    /// Number of chapters in the current file.
+    Chapters,
+    "chapters",
+    i64
+);
+property!(
+    /// All chapters of the current file, with their titles and start times.
+    ChapterList,
+    "chapter-list",
+    Node
+);
+property!(
+    /// Current position in the playlist, starting at `0`.
+    PlaylistPos,
+    "playlist-pos",
+    i64,
+    writable
+);
+property!(
+    /// Number of entries in the playlist.
+    PlaylistCount,
+    "playlist-count",
+    i64
+);
+property!(
+    /// All playlist entries.
+    Playlist,
+    "playlist",
+    Node
+);
+
+impl Mpv {
+    /// Get the value of the typed property `P`.
+    pub fn get<P: Property>(&self) -> Result<P::Value> {
+        self.get_property(P::NAME)
+    }
+
+    /// Set the value of the typed property `P`.
+    pub fn set<P>(&self, value: P::Value) -> Result<()>
+    where
+        P: WritableProperty,
+        P::Value: SetData,
+    {
+        self.set_property(P::NAME, value)
+    }
+}
+
+impl<'parent> EventContext<'parent> {
+    /// Observe the typed property `P` for changes. See `observe_property`.
+    pub fn observe<P: Property>(&self, id: u64) -> Result<()> {
+        self.observe_property(P::NAME, <P::Value as GetData>::get_format(), id)
+    }
+}
@@ -1,252 +1,187 @@
 use super::*;
 
-use std::alloc::{self, Layout};
+mod providers;
+
+pub use self::providers::*;
+
 use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
-use std::mem;
 use std::os::raw as ctype;
-use std::panic;
-use std::panic::RefUnwindSafe;
-use std::ptr::{self, NonNull};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::NonNull;
 use std::slice;
-use std::sync::{atomic::Ordering, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 impl Mpv {
     /// Create a context with which custom protocols can be registered.
-    ///
-    /// # Panics
-    /// Panics if a context already exists
-    pub fn create_protocol_context<T, U>(&self) -> ProtocolContext<T, U>
-    where
-        T: RefUnwindSafe,
-        U: RefUnwindSafe,
-    {
-        if self
-            .protocols_guard
-            .compare_and_swap(false, true, Ordering::AcqRel)
-        {
-            panic!("Protocol context already created")
-        } else {
-            ProtocolContext::new(self.ctx, PhantomData::<&Self>)
+    /// Any number of protocol contexts can exist at the same time.
+    pub fn create_protocol_context(&self) -> ProtocolContext {
+        ProtocolContext {
+            ctx: self.ctx,
+            _does_not_outlive: PhantomData::<&Self>,
         }
     }
 }
 
-/// Return a persistent `T` that is passed to all other `Stream*` functions, panic on errors.
-pub type StreamOpen<T, U> = fn(&mut U, &str) -> T;
-/// Do any necessary cleanup.
-pub type StreamClose<T> = fn(Box<T>);
-/// Seek to the given offset. Return the new offset, or either `MpvError::Generic` if seeking
-/// failed or panic.
-pub type StreamSeek<T> = fn(&mut T, i64) -> i64;
-/// Target buffer with fixed capacity.
-/// Return either the number of read bytes, `0` on EOF, or either `-1` or panic on error.
-pub type StreamRead<T> = fn(&mut T, &mut [ctype::c_char]) -> i64;
-/// Return the total size of the stream in bytes. Panic on error.
-pub type StreamSize<T> = fn(&mut T) -> i64;
-
-unsafe extern "C" fn open_wrapper<T, U>(
+/// A function that interrupts blocking reads of a stream. It is called from another thread than
+/// the reads, and must not block.
+pub type CancelFn = Box<dyn Fn() + Send + Sync>;
+
+/// Opens the streams of a custom protocol, registered with `ProtocolContext::register`.
+///
+/// All functions are called from mpv's threads, and must not call any mpv API.
+/// Panics are caught, and reported to mpv as errors.
+pub trait StreamProvider: Send + Sync + 'static {
+    /// A stream opened by this provider. Reads, seeks and the size are passed through to mpv.
+    type Stream: Read + Seek + Send;
+
+    /// Open the stream for `uri`, which includes the protocol, e.g. `kawa://intro.mkv`.
+    fn open(&self, uri: &str) -> io::Result<Self::Stream>;
+
+    /// Return a function that interrupts blocking reads of `stream`, which is called once mpv
+    /// cancels the stream, e.g. on `stop`. Independently of this, all reads and seeks fail once
+    /// the stream was cancelled.
+    fn cancel_fn(&self, _stream: &Self::Stream) -> Option<CancelFn> {
+        None
+    }
+}
+
+/// The cookie passed to all stream callbacks, owning the stream.
+struct StreamCookie<S> {
+    stream: S,
+    cancelled: AtomicBool,
+    cancel_fn: Option<CancelFn>,
+}
+
+unsafe extern "C" fn open_wrapper<P: StreamProvider>(
     user_data: *mut ctype::c_void,
     uri: *mut ctype::c_char,
     info: *mut libmpv_sys::mpv_stream_cb_info,
-) -> ctype::c_int
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = user_data as *mut ProtocolData<T, U>;
-
-    (*info).cookie = user_data;
-    (*info).read_fn = Some(read_wrapper::<T, U>);
-    (*info).seek_fn = Some(seek_wrapper::<T, U>);
-    (*info).size_fn = Some(size_wrapper::<T, U>);
-    (*info).close_fn = Some(close_wrapper::<T, U>);
-
-    let ret = panic::catch_unwind(|| {
-        let uri = mpv_cstr_to_str!(uri as *const _).unwrap();
-        ptr::write(
-            (*data).cookie,
-            ((*data).open_fn)(&mut (*data).user_data, uri),
-        );
-    });
-
-    if ret.is_ok() {
+) -> ctype::c_int {
+    let provider = &*(user_data as *const P);
+
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        let uri = mpv_cstr_to_str!(uri as *const _)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let stream = provider.open(uri)?;
+        let cancel_fn = provider.cancel_fn(&stream);
+        Ok::<_, io::Error>(StreamCookie {
+            stream,
+            cancelled: AtomicBool::new(false),
+            cancel_fn,
+        })
+    }));
+
+    if let Ok(Ok(cookie)) = ret {
+        (*info).cookie = Box::into_raw(Box::new(cookie)) as *mut _;
+        (*info).read_fn = Some(read_wrapper::<P::Stream>);
+        (*info).seek_fn = Some(seek_wrapper::<P::Stream>);
+        (*info).size_fn = Some(size_wrapper::<P::Stream>);
+        (*info).close_fn = Some(close_wrapper::<P::Stream>);
+        (*info).cancel_fn = Some(cancel_wrapper::<P::Stream>);
         0
     } else {
-        mpv_error::Generic as _
+        mpv_error::LoadingFailed as _
     }
 }
 
-unsafe extern "C" fn read_wrapper<T, U>(
+unsafe extern "C" fn read_wrapper<S: Read + Seek>(
     cookie: *mut ctype::c_void,
     buf: *mut ctype::c_char,
     nbytes: u64,
-) -> i64
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
-
-    let ret = panic::catch_unwind(|| {
-        let slice = slice::from_raw_parts_mut(buf, nbytes as _);
-        ((*data).read_fn)(&mut *(*data).cookie, slice)
-    });
-    if let Ok(ret) = ret {
-        ret
-    } else {
-        -1
-    }
-}
-
-unsafe extern "C" fn seek_wrapper<T, U>(cookie: *mut ctype::c_void, offset: i64) -> i64
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
-
-    if (*data).seek_fn.is_none() {
-        return mpv_error::Unsupported as _;
+) -> i64 {
+    let cookie = cookie as *mut StreamCookie<S>;
+    if (*cookie).cancelled.load(Ordering::Acquire) {
+        return -1;
     }
 
-    let ret =
-        panic::catch_unwind(|| (*(*data).seek_fn.as_ref().unwrap())(&mut *(*data).cookie, offset));
-    if let Ok(ret) = ret {
-        ret
-    } else {
-        mpv_error::Generic as _
+    let stream = &mut (*cookie).stream;
+    let buf = slice::from_raw_parts_mut(buf as *mut u8, nbytes as _);
+    match panic::catch_unwind(AssertUnwindSafe(|| stream.read(buf))) {
+        Ok(Ok(read)) => read as _,
+        _ => -1,
     }
 }
 
-unsafe extern "C" fn size_wrapper<T, U>(cookie: *mut ctype::c_void) -> i64
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = cookie as *mut ProtocolData<T, U>;
-
-    if (*data).size_fn.is_none() {
-        return mpv_error::Unsupported as _;
+unsafe extern "C" fn seek_wrapper<S: Read + Seek>(cookie: *mut ctype::c_void, offset: i64) -> i64 {
+    let cookie = cookie as *mut StreamCookie<S>;
+    if (*cookie).cancelled.load(Ordering::Acquire) || offset < 0 {
+        return mpv_error::Generic as _;
     }
 
-    let ret = panic::catch_unwind(|| (*(*data).size_fn.as_ref().unwrap())(&mut *(*data).cookie));
-    if let Ok(ret) = ret {
-        ret
-    } else {
-        mpv_error::Unsupported as _
+    let stream = &mut (*cookie).stream;
+    match panic::catch_unwind(AssertUnwindSafe(|| {
+        stream.seek(SeekFrom::Start(offset as _))
+    })) {
+        Ok(Ok(offset)) => offset as _,
+        _ => mpv_error::Generic as _,
     }
 }
 
-#[allow(unused_must_use)]
-unsafe extern "C" fn close_wrapper<T, U>(cookie: *mut ctype::c_void)
-where
-    T: RefUnwindSafe,
-    U: RefUnwindSafe,
-{
-    let data = Box::from_raw(cookie as *mut ProtocolData<T, U>);
+unsafe extern "C" fn size_wrapper<S: Read + Seek>(cookie: *mut ctype::c_void) -> i64 {
+    let cookie = cookie as *mut StreamCookie<S>;
+
+    let stream = &mut (*cookie).stream;
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        let offset = stream.stream_position()?;
+        let size = stream.seek(SeekFrom::End(0))?;
+        stream.seek(SeekFrom::Start(offset))?;
+        Ok::<_, io::Error>(size)
+    }));
+    match ret {
+        Ok(Ok(size)) => size as _,
+        _ => mpv_error::Unsupported as _,
+    }
+}
 
-    panic::catch_unwind(|| ((*data).close_fn)(Box::from_raw((*data).cookie)));
+unsafe extern "C" fn close_wrapper<S: Read + Seek>(cookie: *mut ctype::c_void) {
+    let cookie = Box::from_raw(cookie as *mut StreamCookie<S>);
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(cookie)));
 }
 
-struct ProtocolData<T, U> {
-    cookie: *mut T,
-    user_data: U,
+unsafe extern "C" fn cancel_wrapper<S: Read + Seek>(cookie: *mut ctype::c_void) {
+    let cookie = cookie as *const StreamCookie<S>;
+    (*cookie).cancelled.store(true, Ordering::Release);
 
-    open_fn: StreamOpen<T, U>,
-    close_fn: StreamClose<T>,
-    read_fn: StreamRead<T>,
-    seek_fn: Option<StreamSeek<T>>,
-    size_fn: Option<StreamSize<T>>,
+    if let Some(cancel_fn) = (*cookie).cancel_fn.as_ref() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(cancel_fn));
+    }
 }
 
-/// This context holds state relevant to custom protocols.
+/// This context is used to register custom protocols.
 /// It is created by calling `Mpv::create_protocol_context`.
-pub struct ProtocolContext<'parent, T: RefUnwindSafe, U: RefUnwindSafe> {
+pub struct ProtocolContext<'parent> {
     ctx: NonNull<libmpv_sys::mpv_handle>,
-    protocols: Mutex<Vec<Protocol<T, U>>>,
     _does_not_outlive: PhantomData<&'parent Mpv>,
 }
 
-unsafe impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> Send for ProtocolContext<'parent, T, U> {}
-unsafe impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> Sync for ProtocolContext<'parent, T, U> {}
+unsafe impl<'parent> Send for ProtocolContext<'parent> {}
+unsafe impl<'parent> Sync for ProtocolContext<'parent> {}
 
-impl<'parent, T: RefUnwindSafe, U: RefUnwindSafe> ProtocolContext<'parent, T, U> {
-    fn new(
-        ctx: NonNull<libmpv_sys::mpv_handle>,
-        marker: PhantomData<&'parent Mpv>,
-    ) -> ProtocolContext<'parent, T, U> {
-        ProtocolContext {
-            ctx,
-            protocols: Mutex::new(Vec::new()),
-            _does_not_outlive: marker,
-        }
-    }
-
-    /// Register a custom `Protocol`. Once a protocol has been registered, it lives as long as
-    /// `Mpv`.
+impl<'parent> ProtocolContext<'parent> {
+    /// Register `provider` for the protocol `name`, e.g. `kawa` to open `kawa://` URIs.
     ///
-    /// Returns `Error::Mpv(MpvError::InvalidParameter)` if a protocol with the same name has
-    /// already been registered.
-    pub fn register(&self, protocol: Protocol<T, U>) -> Result<()> {
-        let mut protocols = self.protocols.lock().unwrap();
-        protocol.register(self.ctx.as_ptr())?;
-        protocols.push(protocol);
-        Ok(())
-    }
-}
-
-/// `Protocol` holds all state used by a custom protocol.
-pub struct Protocol<T: Sized + RefUnwindSafe, U: RefUnwindSafe> {
-    name: String,
-    data: *mut ProtocolData<T, U>,
-}
-
-impl<T: RefUnwindSafe, U: RefUnwindSafe> Protocol<T, U> {
-    /// `name` is the prefix of the protocol, e.g. `name://path`.
+    /// mpv can not unregister protocols, and may open streams as long as the core exists, so the
+    /// provider is kept alive for the rest of the process.
     ///
-    /// `user_data` is data that will be passed to `open_fn`.
-    ///
-    /// # Safety
-    /// Do not call libmpv functions in any supplied function.
-    /// All panics of the provided functions are catched and can be used as generic error returns.
-    pub unsafe fn new(
-        name: String,
-        user_data: U,
-        open_fn: StreamOpen<T, U>,
-        close_fn: StreamClose<T>,
-        read_fn: StreamRead<T>,
-        seek_fn: Option<StreamSeek<T>>,
-        size_fn: Option<StreamSize<T>>,
-    ) -> Protocol<T, U> {
-        let c_layout = Layout::from_size_align(mem::size_of::<T>(), mem::align_of::<T>()).unwrap();
-        let cookie = alloc::alloc(c_layout) as *mut T;
-        let data = Box::into_raw(Box::new(ProtocolData {
-            cookie,
-            user_data,
-
-            open_fn,
-            close_fn,
-            read_fn,
-            seek_fn,
-            size_fn,
-        }));
-
-        Protocol { name, data }
-    }
-
-    fn register(&self, ctx: *mut libmpv_sys::mpv_handle) -> Result<()> {
-        let name = CString::new(&self.name[..])?;
-        unsafe {
-            mpv_err(
-                (),
-                libmpv_sys::mpv_stream_cb_add_ro(
-                    ctx,
-                    name.as_ptr(),
-                    self.data as *mut _,
-                    Some(open_wrapper::<T, U>),
-                ),
+    /// Returns an `ErrorKind::InvalidParameter` error if a protocol with the same name has
+    /// already been registered.
+    pub fn register<P: StreamProvider>(&self, name: &str, provider: P) -> Result<()> {
+        let name = CString::new(name)?;
+        let provider = Box::into_raw(Box::new(provider));
+
+        let ret = unsafe {
+            libmpv_sys::mpv_stream_cb_add_ro(
+                self.ctx.as_ptr(),
+                name.as_ptr(),
+                provider as *mut _,
+                Some(open_wrapper::<P>),
             )
+        };
+        if ret != 0 {
+            drop(unsafe { Box::from_raw(provider) });
         }
+        mpv_err((), ret)
     }
 }
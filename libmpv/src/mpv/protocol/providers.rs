@@ -0,0 +1,294 @@
+use super::StreamProvider;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::sync::{Arc, RwLock};
+
+/// The part of `uri` after the protocol, e.g. `intro.mkv` for `memory://intro.mkv`.
+fn strip_protocol(uri: &str) -> &str {
+    match uri.find("://") {
+        Some(idx) => &uri[idx + 3..],
+        None => uri,
+    }
+}
+
+fn invalid_uri(uri: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid uri: {}", uri))
+}
+
+/// Plays data registered in memory, e.g. `memory://intro` after inserting `intro`.
+///
+/// The provider is a cheap handle to the shared entries, so a clone can be registered while
+/// another one is kept to insert data. Open streams keep their data alive after it is removed.
+#[derive(Clone, Default)]
+pub struct MemoryProvider {
+    entries: Arc<RwLock<HashMap<String, Arc<[u8]>>>>,
+}
+
+impl MemoryProvider {
+    pub fn new() -> MemoryProvider {
+        MemoryProvider::default()
+    }
+
+    /// Register `data` under `name`, returning the data it replaced.
+    pub fn insert<D: Into<Arc<[u8]>>>(&self, name: &str, data: D) -> Option<Arc<[u8]>> {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(name.to_owned(), data.into())
+    }
+
+    /// Remove the data registered under `name`.
+    pub fn remove(&self, name: &str) -> Option<Arc<[u8]>> {
+        self.entries.write().unwrap().remove(name)
+    }
+}
+
+impl StreamProvider for MemoryProvider {
+    type Stream = Cursor<Arc<[u8]>>;
+
+    fn open(&self, uri: &str) -> io::Result<Self::Stream> {
+        let name = strip_protocol(uri);
+        match self.entries.read().unwrap().get(name) {
+            Some(data) => Ok(Cursor::new(data.clone())),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no data registered for {}", name),
+            )),
+        }
+    }
+}
+
+/// A stream of `len` bytes of `inner`, starting at `start`.
+pub struct RangeStream<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: Seek> RangeStream<R> {
+    /// Create a stream of the range, which is clamped to the end of `inner`.
+    pub fn new(mut inner: R, start: u64, len: Option<u64>) -> io::Result<RangeStream<R>> {
+        let end = inner.seek(SeekFrom::End(0))?;
+        let start = start.min(end);
+        let len = len.unwrap_or(u64::MAX).min(end - start);
+        inner.seek(SeekFrom::Start(start))?;
+        Ok(RangeStream {
+            inner,
+            start,
+            len,
+            pos: 0,
+        })
+    }
+}
+
+impl<R: Read> Read for RangeStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = (self.len - self.pos).min(buf.len() as u64) as usize;
+        let read = self.inner.read(&mut buf[..remaining])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for RangeStream<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => offset_by(self.pos, offset),
+            SeekFrom::End(offset) => offset_by(self.len, offset),
+        };
+        let pos = pos
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the range"))?;
+
+        // Like files, seeking past the end is allowed, reads then return nothing.
+        let pos = pos.min(self.len);
+        self.inner.seek(SeekFrom::Start(self.start + pos))?;
+        self.pos = pos;
+        Ok(pos)
+    }
+}
+
+fn offset_by(base: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        base.checked_sub(offset.unsigned_abs())
+    } else {
+        base.checked_add(offset as u64)
+    }
+}
+
+/// Plays a byte range of a file, e.g. `range:///media/concat.bin?offset=1024&len=4096`.
+/// `offset` defaults to the start and `len` to the rest of the file.
+#[derive(Clone, Copy, Default)]
+pub struct RangeProvider;
+
+impl StreamProvider for RangeProvider {
+    type Stream = RangeStream<File>;
+
+    fn open(&self, uri: &str) -> io::Result<Self::Stream> {
+        let (path, offset, len) = parse_range_uri(uri)?;
+        RangeStream::new(File::open(path)?, offset, len)
+    }
+}
+
+/// Split a `range://` uri into the path, `offset` and `len`.
+fn parse_range_uri(uri: &str) -> io::Result<(&str, u64, Option<u64>)> {
+    let rest = strip_protocol(uri);
+    let (path, query) = match rest.rfind('?') {
+        Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+        None => (rest, ""),
+    };
+
+    let mut offset = 0;
+    let mut len = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut pair = pair.splitn(2, '=');
+        let key = pair.next().unwrap_or_default();
+        let value = pair.next().and_then(|value| value.parse::<u64>().ok());
+        match (key, value) {
+            ("offset", Some(value)) => offset = value,
+            ("len", Some(value)) => len = Some(value),
+            _ => return Err(invalid_uri(uri)),
+        }
+    }
+    Ok((path, offset, len))
+}
+
+/// Split a `zip://` uri into the path of the archive and the name of the member.
+#[cfg(feature = "zip")]
+fn parse_zip_uri(uri: &str) -> io::Result<(&str, &str)> {
+    let rest = strip_protocol(uri);
+    let idx = rest.find('#').ok_or_else(|| invalid_uri(uri))?;
+    Ok((&rest[..idx], &rest[idx + 1..]))
+}
+
+/// A member of a zip archive opened by `ZipProvider`.
+#[cfg(feature = "zip")]
+pub enum ZipStream {
+    /// An uncompressed member, read directly from the archive.
+    Stored(RangeStream<File>),
+    /// A compressed member, which is decompressed into memory when opened.
+    Decompressed(Cursor<Vec<u8>>),
+}
+
+#[cfg(feature = "zip")]
+impl Read for ZipStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ZipStream::Stored(stream) => stream.read(buf),
+            ZipStream::Decompressed(stream) => stream.read(buf),
+        }
+    }
+}
+
+#[cfg(feature = "zip")]
+impl Seek for ZipStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            ZipStream::Stored(stream) => stream.seek(pos),
+            ZipStream::Decompressed(stream) => stream.seek(pos),
+        }
+    }
+}
+
+/// Plays a member of a zip archive, e.g. `zip://assets/bundle.zip#videos/intro.mkv`.
+/// Available with the `zip` feature.
+#[cfg(feature = "zip")]
+#[derive(Clone, Copy, Default)]
+pub struct ZipProvider;
+
+#[cfg(feature = "zip")]
+impl StreamProvider for ZipProvider {
+    type Stream = ZipStream;
+
+    fn open(&self, uri: &str) -> io::Result<Self::Stream> {
+        let (path, member) = parse_zip_uri(uri)?;
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let mut file = archive.by_name(member)?;
+        if file.compression() == zip::CompressionMethod::Stored {
+            let (start, len) = (file.data_start(), file.size());
+            drop(file);
+            let stream = RangeStream::new(archive.into_inner(), start, Some(len))?;
+            Ok(ZipStream::Stored(stream))
+        } else {
+            // The size in the header is not trusted for preallocation, as it could be anything.
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            Ok(ZipStream::Decompressed(Cursor::new(data)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_uri() {
+        let parse = |uri| parse_range_uri(uri).map_err(|err| err.kind());
+        assert_eq!(parse("range:///a/b.bin"), Ok(("/a/b.bin", 0, None)));
+        assert_eq!(
+            parse("range:///a/b.bin?offset=1024&len=4096"),
+            Ok(("/a/b.bin", 1024, Some(4096)))
+        );
+        assert_eq!(
+            parse("range:///a?b.bin?len=8"),
+            Ok(("/a?b.bin", 0, Some(8)))
+        );
+        assert_eq!(
+            parse("range:///a/b.bin?offset=-1"),
+            Err(io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            parse("range:///a/b.bin?start=0"),
+            Err(io::ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "zip")]
+    fn zip_uri() {
+        let parse = |uri| parse_zip_uri(uri).map_err(|err| err.kind());
+        assert_eq!(
+            parse("zip://assets/bundle.zip#videos/intro.mkv"),
+            Ok(("assets/bundle.zip", "videos/intro.mkv"))
+        );
+        assert_eq!(parse("zip://a.zip#b#c"), Ok(("a.zip", "b#c")));
+        assert_eq!(parse("zip://a.zip"), Err(io::ErrorKind::InvalidInput));
+    }
+
+    fn range(start: u64, len: Option<u64>) -> RangeStream<Cursor<Vec<u8>>> {
+        RangeStream::new(Cursor::new((0..10).collect()), start, len).unwrap()
+    }
+
+    fn read_all<R: Read>(stream: &mut R) -> Vec<u8> {
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn range_stream_read() {
+        assert_eq!(read_all(&mut range(2, Some(3))), [2, 3, 4]);
+        assert_eq!(read_all(&mut range(7, None)), [7, 8, 9]);
+        assert_eq!(read_all(&mut range(7, Some(100))), [7, 8, 9]);
+        assert_eq!(read_all(&mut range(20, None)), []);
+    }
+
+    #[test]
+    fn range_stream_seek() {
+        let mut stream = range(2, Some(5));
+        assert_eq!(stream.seek(SeekFrom::Start(1)).unwrap(), 1);
+        assert_eq!(read_all(&mut stream), [3, 4, 5, 6]);
+
+        assert_eq!(stream.seek(SeekFrom::End(-2)).unwrap(), 3);
+        assert_eq!(stream.seek(SeekFrom::Current(-1)).unwrap(), 2);
+        assert_eq!(read_all(&mut stream), [4, 5, 6]);
+
+        assert_eq!(stream.seek(SeekFrom::Start(100)).unwrap(), 5);
+        assert_eq!(read_all(&mut stream), []);
+        assert!(stream.seek(SeekFrom::Current(-6)).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use super::*;
+
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw as ctype;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+
+impl Mpv {
+    /// Create a render context with the given `params`. At least `RenderParam::ApiType` has to be
+    /// supplied, and for OpenGL also `RenderParam::OpenGlInitParams`.
+    ///
+    /// For more information, see the mpv-sys docs of `mpv_render_context_create`.
+    pub fn create_render_context<'a, I>(&self, params: I) -> Result<RenderContext>
+    where
+        I: IntoIterator<Item = RenderParam<'a>>,
+    {
+        let mut raw = RawRenderParams::new(params);
+        let mut ctx = ptr::null_mut();
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_render_context_create(&mut ctx, self.ctx.as_ptr(), raw.as_mut_ptr())
+        })?;
+
+        Ok(RenderContext {
+            ctx: NonNull::new(ctx).ok_or(Error::Null)?,
+            update_callback: None,
+            _does_not_outlive: PhantomData::<&Self>,
+        })
+    }
+}
+
+/// Flags returned by `RenderContext::update`.
+pub use libmpv_sys::mpv_render_update_flag as RenderUpdateFlag;
+pub mod mpv_render_update_flag {
+    pub use libmpv_sys::mpv_render_update_flag_MPV_RENDER_UPDATE_FRAME as Frame;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The rendering API a `RenderContext` uses.
+pub enum RenderApiType {
+    /// Render with OpenGL, see `render_gl.h`.
+    OpenGl,
+    /// Render on the CPU into memory, see `RenderContext::render_sw`.
+    Software,
+}
+
+impl RenderApiType {
+    fn as_ptr(&self) -> *mut ctype::c_void {
+        match *self {
+            RenderApiType::OpenGl => libmpv_sys::MPV_RENDER_API_TYPE_OPENGL.as_ptr() as *mut _,
+            RenderApiType::Software => libmpv_sys::MPV_RENDER_API_TYPE_SW.as_ptr() as *mut _,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Pixel format of a surface rendered by the software renderer.
+pub enum SwFormat {
+    /// 4 bytes per pixel, `r` at the lowest address, the last byte is garbage.
+    Rgb0,
+    /// 4 bytes per pixel, `b` at the lowest address, the last byte is garbage.
+    Bgr0,
+    /// 4 bytes per pixel, the first byte is garbage, `b` at the second lowest address.
+    ZeroBgr,
+    /// 4 bytes per pixel, the first byte is garbage, `r` at the second lowest address.
+    ZeroRgb,
+    /// 3 bytes per pixel, strongly discouraged because it is very slow.
+    Rgb24,
+}
+
+impl SwFormat {
+    /// The number of bytes a single pixel takes up.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            SwFormat::Rgb24 => 3,
+            _ => 4,
+        }
+    }
+
+    fn as_ptr(&self) -> *mut ctype::c_void {
+        let name: &'static [u8] = match *self {
+            SwFormat::Rgb0 => b"rgb0\0",
+            SwFormat::Bgr0 => b"bgr0\0",
+            SwFormat::ZeroBgr => b"0bgr\0",
+            SwFormat::ZeroRgb => b"0rgb\0",
+            SwFormat::Rgb24 => b"rgb24\0",
+        };
+        name.as_ptr() as *mut _
+    }
+}
+
+/// Parameters used to initialize the OpenGL backend.
+pub struct OpenGlInitParams<'a> {
+    /// Resolve an OpenGL function by name, e.g. with `glXGetProcAddressARB`.
+    /// Return a null pointer if the function is not available.
+    ///
+    /// This is only called while the render context is being created.
+    pub get_proc_address: &'a dyn Fn(&str) -> *mut ctype::c_void,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An OpenGL framebuffer to render into.
+pub struct OpenGlFbo {
+    /// Framebuffer object name, `0` for the default framebuffer.
+    pub fbo: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Internal texture format of the framebuffer, `0` if unknown.
+    pub internal_format: i32,
+}
+
+/// A parameter passed to `Mpv::create_render_context` or `RenderContext::render`.
+pub enum RenderParam<'a> {
+    /// The API to render with. Only valid for `Mpv::create_render_context`.
+    ApiType(RenderApiType),
+    /// Only valid for `Mpv::create_render_context`.
+    OpenGlInitParams(OpenGlInitParams<'a>),
+    /// The framebuffer to render into. Only valid for `RenderContext::render`.
+    OpenGlFbo(OpenGlFbo),
+    /// Flip the rendered image vertically, needed for the default framebuffer.
+    FlipY(bool),
+    /// Bit depth of the target surface.
+    Depth(i32),
+    /// Opt into the advanced control mode, see the mpv-sys docs of `render.h`.
+    /// Only valid for `Mpv::create_render_context`.
+    AdvancedControl(bool),
+    /// Whether `RenderContext::render` waits until the frame's display time, defaults to `true`.
+    BlockForTargetTime(bool),
+    /// Update the internal state, but skip the actual rendering.
+    SkipRendering(bool),
+}
+
+type FreeFn = unsafe fn(*mut ctype::c_void);
+
+unsafe fn free_void_data<T>(ptr: *mut ctype::c_void) {
+    drop(Box::from_raw(ptr as *mut T));
+}
+
+unsafe extern "C" fn get_proc_address_wrapper(
+    ctx: *mut ctype::c_void,
+    name: *const ctype::c_char,
+) -> *mut ctype::c_void {
+    let get_proc_address = &*(ctx as *const &dyn Fn(&str) -> *mut ctype::c_void);
+
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        CStr::from_ptr(name)
+            .to_str()
+            .map(get_proc_address)
+            .unwrap_or(ptr::null_mut())
+    }));
+    ret.unwrap_or(ptr::null_mut())
+}
+
+/// `mpv_render_param`s with their data, which is freed once this is dropped.
+struct RawRenderParams {
+    params: Vec<libmpv_sys::mpv_render_param>,
+    allocations: Vec<(*mut ctype::c_void, FreeFn)>,
+}
+
+impl RawRenderParams {
+    fn new<'a, I: IntoIterator<Item = RenderParam<'a>>>(params: I) -> RawRenderParams {
+        let mut raw = RawRenderParams {
+            params: Vec::new(),
+            allocations: Vec::new(),
+        };
+
+        for param in params {
+            match param {
+                RenderParam::ApiType(api_type) => raw.push_static(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_API_TYPE,
+                    api_type.as_ptr(),
+                ),
+                RenderParam::OpenGlInitParams(init_params) => {
+                    let ctx = raw.alloc(init_params.get_proc_address);
+                    raw.push(
+                        libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_OPENGL_INIT_PARAMS,
+                        libmpv_sys::mpv_opengl_init_params {
+                            get_proc_address: Some(get_proc_address_wrapper),
+                            get_proc_address_ctx: ctx,
+                            extra_exts: ptr::null(),
+                        },
+                    );
+                }
+                RenderParam::OpenGlFbo(fbo) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_OPENGL_FBO,
+                    libmpv_sys::mpv_opengl_fbo {
+                        fbo: fbo.fbo,
+                        w: fbo.width,
+                        h: fbo.height,
+                        internal_format: fbo.internal_format,
+                    },
+                ),
+                RenderParam::FlipY(flip) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_FLIP_Y,
+                    flip as ctype::c_int,
+                ),
+                RenderParam::Depth(depth) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_DEPTH,
+                    depth as ctype::c_int,
+                ),
+                RenderParam::AdvancedControl(enable) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_ADVANCED_CONTROL,
+                    enable as ctype::c_int,
+                ),
+                RenderParam::BlockForTargetTime(block) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_BLOCK_FOR_TARGET_TIME,
+                    block as ctype::c_int,
+                ),
+                RenderParam::SkipRendering(skip) => raw.push(
+                    libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SKIP_RENDERING,
+                    skip as ctype::c_int,
+                ),
+            }
+        }
+
+        raw
+    }
+
+    /// Move `data` to the heap, it is freed once `self` is dropped.
+    fn alloc<T>(&mut self, data: T) -> *mut ctype::c_void {
+        let ptr = Box::into_raw(Box::new(data)) as *mut ctype::c_void;
+        self.allocations.push((ptr, free_void_data::<T>));
+        ptr
+    }
+
+    fn push<T>(&mut self, type_: libmpv_sys::mpv_render_param_type, data: T) {
+        let data = self.alloc(data);
+        self.params.push(libmpv_sys::mpv_render_param { type_, data });
+    }
+
+    fn push_static(&mut self, type_: libmpv_sys::mpv_render_param_type, data: *mut ctype::c_void) {
+        self.params.push(libmpv_sys::mpv_render_param { type_, data });
+    }
+
+    /// Terminate the list, and return a pointer usable by `mpv_render_context_*` functions.
+    fn as_mut_ptr(&mut self) -> *mut libmpv_sys::mpv_render_param {
+        self.push_static(
+            libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_INVALID,
+            ptr::null_mut(),
+        );
+        self.params.as_mut_ptr()
+    }
+}
+
+impl Drop for RawRenderParams {
+    fn drop(&mut self) {
+        for (ptr, free_fn) in self.allocations.drain(..) {
+            unsafe { free_fn(ptr) };
+        }
+    }
+}
+
+/// A context used to render video frames with a user-provided graphics API.
+/// It is created by calling `Mpv::create_render_context`.
+pub struct RenderContext<'parent> {
+    ctx: NonNull<libmpv_sys::mpv_render_context>,
+    update_callback: Option<Box<UpdateCallback>>,
+    _does_not_outlive: PhantomData<&'parent Mpv>,
+}
+
+type UpdateCallback = Box<dyn Fn() + Send>;
+
+unsafe extern "C" fn update_callback_wrapper(cb_ctx: *mut ctype::c_void) {
+    let callback = &*(cb_ctx as *const UpdateCallback);
+    let _ = panic::catch_unwind(AssertUnwindSafe(callback));
+}
+
+impl<'parent> Drop for RenderContext<'parent> {
+    fn drop(&mut self) {
+        // The update callback is freed afterwards, mpv does not call it once the context is gone.
+        unsafe { libmpv_sys::mpv_render_context_free(self.ctx.as_ptr()) };
+    }
+}
+
+impl<'parent> RenderContext<'parent> {
+    /// Render a frame with the given `params`, e.g. `RenderParam::OpenGlFbo` and
+    /// `RenderParam::FlipY` for OpenGL.
+    ///
+    /// The OpenGL context has to be current on the calling thread.
+    pub fn render<'a, I>(&self, params: I) -> Result<()>
+    where
+        I: IntoIterator<Item = RenderParam<'a>>,
+    {
+        let mut raw = RawRenderParams::new(params);
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_render_context_render(self.ctx.as_ptr(), raw.as_mut_ptr())
+        })
+    }
+
+    /// Render a frame into the OpenGL framebuffer `fbo`.
+    /// Pass `flip_y = true` when rendering to the default framebuffer.
+    pub fn render_fbo(&self, fbo: i32, width: i32, height: i32, flip_y: bool) -> Result<()> {
+        self.render(vec![
+            RenderParam::OpenGlFbo(OpenGlFbo {
+                fbo,
+                width,
+                height,
+                internal_format: 0,
+            }),
+            RenderParam::FlipY(flip_y),
+        ])
+    }
+
+    /// Render a frame with the software renderer into the caller-owned `buffer`, which is
+    /// interpreted as `height` lines of `stride` bytes each, in the given `format`.
+    /// The video is scaled to `width`x`height`, with black bars added if the aspect ratio differs.
+    ///
+    /// The context has to be created with `RenderApiType::Software`. For best performance,
+    /// `stride` and the start of `buffer` should be aligned to 64 bytes.
+    ///
+    /// Returns an `ErrorKind::InvalidParameter` error if the dimensions are not positive,
+    /// if `stride` cannot hold a line of `width` pixels, if `buffer` is too small, or if it is
+    /// not aligned to 4 bytes for the 4 byte formats.
+    pub fn render_sw(
+        &self,
+        width: i32,
+        height: i32,
+        format: SwFormat,
+        stride: usize,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        check_sw_buffer(width, height, format, stride, buffer)?;
+
+        let mut raw = RawRenderParams::new(None);
+        raw.push(
+            libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_SIZE,
+            [width as ctype::c_int, height as ctype::c_int],
+        );
+        raw.push_static(
+            libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_FORMAT,
+            format.as_ptr(),
+        );
+        raw.push(
+            libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_STRIDE,
+            stride,
+        );
+        raw.push_static(
+            libmpv_sys::mpv_render_param_type_MPV_RENDER_PARAM_SW_POINTER,
+            buffer.as_mut_ptr() as *mut _,
+        );
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_render_context_render(self.ctx.as_ptr(), raw.as_mut_ptr())
+        })
+    }
+
+    /// Set the `callback` that is called when a new frame should be rendered, replacing any
+    /// previous one.
+    ///
+    /// The callback may be called from any thread. It must not call any mpv API, and should
+    /// only notify the render thread, which then calls `update`.
+    pub fn set_update_callback<F: Fn() + Send + 'static>(&mut self, callback: F) {
+        let callback: Box<UpdateCallback> = Box::new(Box::new(callback));
+        unsafe {
+            libmpv_sys::mpv_render_context_set_update_callback(
+                self.ctx.as_ptr(),
+                Some(update_callback_wrapper),
+                &*callback as *const UpdateCallback as *mut _,
+            )
+        };
+        self.update_callback = Some(callback);
+    }
+
+    /// Return a bitset of `mpv_render_update_flag`s describing what has to be done.
+    /// If `mpv_render_update_flag::Frame` is set, call `render`.
+    ///
+    /// Has to be called on the render thread after each update callback.
+    pub fn update(&self) -> u64 {
+        unsafe { libmpv_sys::mpv_render_context_update(self.ctx.as_ptr()) }
+    }
+
+    /// Tell the renderer that a frame was just flipped, which is optional but improves frame
+    /// timing. Call it right after swapping buffers.
+    pub fn report_swap(&self) {
+        unsafe { libmpv_sys::mpv_render_context_report_swap(self.ctx.as_ptr()) };
+    }
+}
+
+/// Check that mpv can write `height` lines of `stride` bytes in `format` into `buffer`, as it
+/// writes every byte up to `stride * height`.
+fn check_sw_buffer(
+    width: i32,
+    height: i32,
+    format: SwFormat,
+    stride: usize,
+    buffer: &[u8],
+) -> Result<()> {
+    let bpp = format.bytes_per_pixel();
+    if width <= 0
+        || height <= 0
+        || stride % bpp != 0
+        || (width as usize)
+            .checked_mul(bpp)
+            .map_or(true, |line| stride < line)
+        || stride
+            .checked_mul(height as usize)
+            .map_or(true, |needed| buffer.len() < needed)
+        || (bpp == 4 && buffer.as_ptr() as usize % 4 != 0)
+    {
+        return Err(Error::from(ErrorKind::InvalidParameter));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sw_buffer() {
+        let buffer = [0u32; 4 * 2];
+        let buffer = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, 32) };
+        assert!(check_sw_buffer(4, 2, SwFormat::Rgb0, 16, buffer).is_ok());
+        assert!(check_sw_buffer(4, 3, SwFormat::Rgb0, 16, buffer).is_err());
+        assert!(check_sw_buffer(5, 2, SwFormat::Rgb0, 16, buffer).is_err());
+        assert!(check_sw_buffer(4, 2, SwFormat::Rgb0, 18, buffer).is_err());
+        assert!(check_sw_buffer(4, 1, SwFormat::Rgb0, 16, &buffer[1..17]).is_err());
+        assert!(check_sw_buffer(0, 2, SwFormat::Rgb0, 16, buffer).is_err());
+    }
+
+    #[test]
+    fn sw_buffer_overflow() {
+        // `stride * height` wraps around to 0, which must not pass for an empty buffer.
+        let stride = usize::MAX / 2 + 1;
+        assert!(check_sw_buffer(1, 2, SwFormat::Rgb0, stride, &[]).is_err());
+    }
+}
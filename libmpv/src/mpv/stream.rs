@@ -0,0 +1,188 @@
+//! An asynchronous interface to mpv, available with the `futures` feature.
+//!
+//! `Mpv::create_event_stream` returns an `EventStream`, which is woken up by mpv's wakeup
+//! callback instead of blocking in `wait_event`. Its `AsyncMpv` handle sends commands and
+//! property requests, whose replies are delivered while the stream is polled.
+//!
+//! Events borrow from the event context, so they are mapped before being yielded, e.g. with
+//! `mpv.create_event_stream(|event| OwnedEvent::try_from(event))`. See
+//! `examples/event_stream.rs`.
+
+use crate::events::{mpv_event_id, Event, EventContext, PropertyData};
+use crate::*;
+
+use futures::channel::oneshot;
+use futures::stream::Stream;
+use futures::task::AtomicWaker;
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+type Reply = Result<Option<Node>>;
+type MapEvent<'parent, T> = Box<dyn FnMut(Event) -> Result<T> + Send + 'parent>;
+
+struct Shared {
+    next_reply_userdata: AtomicU64,
+    replies: Mutex<HashMap<u64, oneshot::Sender<Reply>>>,
+}
+
+impl Mpv {
+    /// Create a stream of events, which creates the event context of this `Mpv`.
+    ///
+    /// Events borrow from the event context, so `map` turns each of them into an item that can
+    /// be yielded by the stream, or an error.
+    ///
+    /// # Panics
+    /// Panics if an event context already exists
+    pub fn create_event_stream<'parent, T, F>(&'parent self, map: F) -> EventStream<'parent, T>
+    where
+        F: FnMut(Event) -> Result<T> + Send + 'parent,
+    {
+        let shared = Arc::new(Shared {
+            next_reply_userdata: AtomicU64::new(FIRST_RESERVED_ID),
+            replies: Mutex::new(HashMap::new()),
+        });
+
+        let mut events = self.create_event_context();
+        let waker = Arc::new(AtomicWaker::new());
+        let wakeup = waker.clone();
+        events.set_wakeup_callback(move || wakeup.wake());
+
+        EventStream {
+            events,
+            mpv: self,
+            waker,
+            shared,
+            map: Box::new(map),
+            done: false,
+        }
+    }
+}
+
+/// A `Stream` of mapped events, which ends after `Event::Shutdown`.
+///
+/// Replies to requests made through `AsyncMpv` are not yielded, but resolve the corresponding
+/// futures, so the stream has to be polled for them to complete.
+pub struct EventStream<'parent, T> {
+    events: EventContext<'parent>,
+    mpv: &'parent Mpv,
+    waker: Arc<AtomicWaker>,
+    shared: Arc<Shared>,
+    map: MapEvent<'parent, T>,
+    done: bool,
+}
+
+impl<'parent, T> EventStream<'parent, T> {
+    /// The underlying event context, e.g. to enable events or observe properties.
+    /// Waiting for events on it directly is not supported.
+    pub fn context(&self) -> &EventContext<'parent> {
+        &self.events
+    }
+
+    /// Get a handle to send asynchronous requests, that are resolved by this stream.
+    pub fn handle(&self) -> AsyncMpv<'parent> {
+        AsyncMpv {
+            mpv: self.mpv,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<'parent, T> Stream for EventStream<'parent, T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        // Register before polling, so that a wakeup in between is not lost.
+        this.waker.register(cx.waker());
+        loop {
+            let (event_id, reply_userdata, result) = match this.events.wait_event_raw(0.0) {
+                Some(event) => event,
+                None => return Poll::Pending,
+            };
+
+            match event_id {
+                mpv_event_id::CommandReply
+                | mpv_event_id::SetPropertyReply
+                | mpv_event_id::GetPropertyReply => {
+                    let sender = this.shared.replies.lock().unwrap().remove(&reply_userdata);
+                    if let Some(sender) = sender {
+                        let reply = result.and_then(|event| match event {
+                            Event::GetPropertyReply {
+                                result: PropertyData::Node(node),
+                                ..
+                            } => node.to_node().map(Some),
+                            _ => Ok(None),
+                        });
+                        // The receiver may have been dropped, if the request was cancelled.
+                        let _ = sender.send(reply);
+                        continue;
+                    }
+                }
+                mpv_event_id::Shutdown => this.done = true,
+                _ => {}
+            }
+
+            return Poll::Ready(Some(result.and_then(&mut this.map)));
+        }
+    }
+}
+
+/// A handle to send asynchronous requests to mpv, whose replies are delivered by polling the
+/// `EventStream` it was created from. If the stream is dropped, pending requests fail.
+#[derive(Clone)]
+pub struct AsyncMpv<'parent> {
+    mpv: &'parent Mpv,
+    shared: Arc<Shared>,
+}
+
+impl<'parent> AsyncMpv<'parent> {
+    async fn request<F: FnOnce(u64) -> Result<()>>(&self, send: F) -> Reply {
+        let reply_userdata = self
+            .shared
+            .next_reply_userdata
+            .fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.shared
+            .replies
+            .lock()
+            .unwrap()
+            .insert(reply_userdata, sender);
+
+        if let Err(err) = send(reply_userdata) {
+            self.shared.replies.lock().unwrap().remove(&reply_userdata);
+            return Err(err);
+        }
+        receiver
+            .await
+            .unwrap_or(Err(Error::from(ErrorKind::Generic)))
+    }
+
+    /// Run a command, see `Mpv::command_async`.
+    pub async fn command(&self, name: &str, args: &[&str]) -> Result<()> {
+        self.request(|id| self.mpv.command_async(name, args, id))
+            .await
+            .map(|_| ())
+    }
+
+    /// Set the value of a property, see `Mpv::set_property_async`.
+    pub async fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
+        self.request(|id| self.mpv.set_property_async(name, data, id))
+            .await
+            .map(|_| ())
+    }
+
+    /// Get the value of a property as a `Node`, see `Mpv::get_property_async`.
+    pub async fn get_property(&self, name: &str) -> Result<Node> {
+        self.request(|id| self.mpv.get_property_async(name, Format::Node, id))
+            .await?
+            .ok_or(Error::Null)
+    }
+}